@@ -1,72 +1,1352 @@
-use data_structures::btree::{BTree, BTreeNodeSnapshot, BTreeSnapshot};
+use data_structures::btree::layout::{self, LayoutConfig, LayoutNode, TreeLayout};
+use data_structures::btree::{
+    BTree, BTreeEvent, BTreeNodeSnapshot, BTreeRecorder, BTreeSnapshot, RangeStep, SearchStep,
+};
 use eframe::egui::{
-    self, Align2, CentralPanel, Color32, FontId, Id, Pos2, Rect, ScrollArea, Sense, Stroke,
-    StrokeKind, TextEdit, TopBottomPanel, Ui, Vec2,
+    self, Align2, CentralPanel, Color32, DragValue, FontId, Id, Painter, Pos2, Rect, ScrollArea,
+    Sense, SidePanel, Stroke, StrokeKind, TextEdit, TopBottomPanel, Ui, Vec2,
 };
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs;
+use std::ops::{Bound, RangeBounds};
 
-const NODE_HEIGHT: f32 = 34.0;
-const NODE_HORIZONTAL_PADDING: f32 = 14.0;
-const HORIZONTAL_GAP: f32 = 30.0;
-const VERTICAL_GAP: f32 = 88.0;
 const CANVAS_PADDING: f32 = 40.0;
+const MIN_ZOOM: f32 = 0.05;
+const MAX_ZOOM: f32 = 4.0;
+/// How strongly one unit of mouse-wheel scrolling zooms.
+const ZOOM_PER_SCROLL: f32 = 0.002;
+/// How long each playback frame stays on screen while playing.
+const FRAME_SECONDS: f64 = 1.2;
+/// How long nodes take to glide from their old place to their new one.
+const MOVE_SECONDS: f64 = 0.45;
+/// How long each step of a range scan stays on screen while playing.
+const SCAN_STEP_SECONDS: f64 = 0.6;
+/// Largest minimum degree offered by the spinners. Bigger nodes no longer fit
+/// on screen side by side.
+const MAX_MIN_DEGREE: usize = 8;
+/// Upper bound on the keys added by one bulk insert, so a typo such as
+/// `0..1000000` does not freeze the window.
+const MAX_BULK_KEYS: usize = 5_000;
+/// Number of history entries kept before the oldest ones are dropped.
+const MAX_HISTORY: usize = 500;
+/// Total number of keys the history entries may hold between them. Every
+/// entry is a full copy of the tree, so large trees keep fewer entries.
+const MAX_HISTORY_KEYS: usize = 250_000;
+/// Minimum degree of the tree the app starts with and scripts replay onto.
+const STARTING_MIN_DEGREE: usize = 2;
+/// Key type of the tree the app starts with and scripts replay onto.
+const STARTING_KEY_TYPE: KeyType = KeyType::Integer;
+
+/// Scale applied to PNG exports, so text stays sharp on high-density screens
+/// and in docs.
+const PNG_SCALE: f32 = 2.0;
+
+const USAGE: &str = "\
+Usage: btree_visualizer [SCRIPT] [--export FILE.svg|FILE.png]...
+
+Opens a window showing a B-tree. SCRIPT is replayed onto it first.
+
+Options:
+  --export FILE  Replay SCRIPT without opening a window and write the tree
+                 to FILE as SVG or PNG. May be given more than once.
+  --help         Show this message.";
 
+/// See `USAGE` for the command line.
 fn main() -> eframe::Result<()> {
+    let Args { script, exports } = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return Ok(());
+        }
+        Err(message) => {
+            eprintln!("{message}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    if !exports.is_empty() {
+        if let Err(message) = export_headless(script.as_deref(), &exports) {
+            eprintln!("{message}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let options = eframe::NativeOptions::default();
     eframe::run_native(
         "B-Tree Visualizer",
         options,
-        Box::new(|_cc| Ok(Box::new(BTreeVisualizerApp::default()))),
+        Box::new(|_cc| {
+            let mut app = BTreeVisualizerApp::default();
+            if let Some(path) = script {
+                app.script_path = path;
+                app.load_script();
+            }
+            Ok(Box::new(app))
+        }),
     )
 }
 
+/// The command line, once parsed.
+#[derive(Debug, PartialEq)]
+struct Args {
+    script: Option<String>,
+    exports: Vec<String>,
+}
+
+/// Read the command line arguments, without the program name. `--help` gives
+/// `None`.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut script = None;
+    let mut exports = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--help" | "-h" => return Ok(None),
+            "--export" => {
+                let path = args.next().ok_or("--export needs a file path")?;
+                exports.push(path);
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{flag}'")),
+            _ if script.is_some() => return Err(format!("unexpected argument '{arg}'")),
+            _ => script = Some(arg),
+        }
+    }
+    Ok(Some(Args { script, exports }))
+}
+
 struct BTreeVisualizerApp {
-    tree: BTree<i32, String>,
+    tree: BTree<Key, String>,
+    /// Type of every key in `tree`. Typed keys are parsed as this type.
+    key_type: KeyType,
+    /// Attached to `tree` so that deletes can report how the tree rebalanced.
+    /// Every operation that makes the tree report events clears it first, so
+    /// it never holds more than the events of one operation.
+    recorder: BTreeRecorder<Key>,
     insert_key: String,
     insert_value: String,
     get_key: String,
+    delete_key: String,
     status: String,
+    /// Replay inserts step by step instead of jumping to the final tree.
+    animate_inserts: bool,
+    /// Frames of the most recent insert, while it is being played back.
+    playback: Option<Playback>,
+    /// Nodes visited by the last Get, shown until the tree changes.
+    search: Vec<SearchStep>,
+    /// Range typed for scans, such as `10..=40`.
+    scan_range: String,
+    /// Walk of the last range scan, shown until the tree changes.
+    scan: Option<Scan>,
+    /// Second tree holding the same keys at another minimum degree, shown
+    /// next to `tree` while side-by-side mode is on.
+    comparison: Option<BTree<Key, String>>,
+    /// Minimum degree used for `comparison` the next time it is built.
+    comparison_degree: usize,
+    main_view: TreeView,
+    comparison_view: TreeView,
+    /// Range for bulk inserts, written as `start..end step`.
+    bulk_range: String,
+    random_count: usize,
+    random_seed: u64,
+    /// Comma-separated keys for bulk inserts.
+    bulk_list: String,
+    /// The tree after every change, oldest first. Undo and redo move
+    /// `history_index` through it; a new change drops everything after it.
+    history: VecDeque<HistoryEntry>,
+    history_index: usize,
+    /// Every operation performed so far, in order. Saved as a script.
+    log: Vec<Operation>,
+    /// File that Save and Load use.
+    script_path: String,
+    /// File that Export writes; `.svg` or `.png`.
+    export_path: String,
+}
+
+/// The tree as it was right after a change.
+///
+/// This is a full copy made with `BTree::clone`, not a snapshot that shares
+/// unchanged nodes with its neighbours, so every change costs O(n) time and
+/// memory for a tree of n keys. That is fine here: a tree that still fits on
+/// screen has at most a few thousand keys, copying it takes far less time
+/// than drawing a frame, and jumping to an entry is one more copy instead of
+/// a replay of every operation before it. `record` bounds the memory.
+struct HistoryEntry {
+    label: String,
+    tree: BTree<Key, String>,
+    key_type: KeyType,
+    /// Length of the operation log when the copy was taken.
+    log_len: usize,
+}
+
+/// The type of the keys in the tree. Every key in one tree has the same type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeyType {
+    Integer,
+    Text,
+    Float,
+}
+
+impl KeyType {
+    const ALL: [KeyType; 3] = [KeyType::Integer, KeyType::Text, KeyType::Float];
+
+    fn name(self) -> &'static str {
+        match self {
+            KeyType::Integer => "i64",
+            KeyType::Text => "String",
+            KeyType::Float => "f64",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|key_type| key_type.name() == name)
+    }
+
+    fn parse(self, text: &str) -> Result<Key, String> {
+        let invalid = || format!("'{text}' is not a valid {}", self.name());
+        match self {
+            KeyType::Integer => text.parse().map(Key::Integer).map_err(|_| invalid()),
+            // Scripts separate words with spaces, so keys must not contain any.
+            KeyType::Text if text.is_empty() || text.contains(char::is_whitespace) => {
+                Err(format!("'{text}' is not a valid String key: use one word"))
+            }
+            KeyType::Text => Ok(Key::Text(text.to_string())),
+            KeyType::Float => text
+                .parse()
+                .map(|value| Key::Float(TotalF64(value)))
+                .map_err(|_| invalid()),
+        }
+    }
+
+    /// The key for a number produced by the bulk insert tools. Text keys
+    /// hold the digits, so `10` sorts before `9`.
+    fn key_from_int(self, value: i64) -> Key {
+        match self {
+            KeyType::Integer => Key::Integer(value),
+            KeyType::Text => Key::Text(value.to_string()),
+            KeyType::Float => Key::Float(TotalF64(value as f64)),
+        }
+    }
+}
+
+/// A key of whichever type the visualizer is set to. Keys of different types
+/// never share a tree; if they did, they would sort by type first.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Key {
+    Integer(i64),
+    Text(String),
+    Float(TotalF64),
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Integer(value) => write!(f, "{value}"),
+            Key::Text(value) => write!(f, "{value}"),
+            Key::Float(TotalF64(value)) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// An `f64` ordered by `f64::total_cmp`, so that it can be a B-tree key.
+/// NaN sorts after every number and `-0.0` sorts before `0.0`.
+#[derive(Clone, Copy, Debug)]
+struct TotalF64(f64);
+
+impl PartialEq for TotalF64 {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TotalF64 {}
+
+impl PartialOrd for TotalF64 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TotalF64 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// A range of keys for a scan: `10..40`, `10..=40`, `10..`, `..40`, `..=40`
+/// or `..`.
+#[derive(Clone, Debug, PartialEq)]
+struct KeyRange {
+    /// Inclusive start, or `None` to start at the smallest key.
+    start: Option<Key>,
+    end: Bound<Key>,
+}
+
+impl KeyRange {
+    /// Parse a range written as in Rust, reading the bounds as `key_type`.
+    fn parse(text: &str, key_type: KeyType) -> Result<Self, String> {
+        let text = text.trim();
+        let (start, end, inclusive) = if let Some((start, end)) = text.split_once("..=") {
+            (start, end, true)
+        } else if let Some((start, end)) = text.split_once("..") {
+            (start, end, false)
+        } else {
+            return Err(format!("'{text}' is not a range such as 10..=40"));
+        };
+        let parse_bound = |text: &str| match text.trim() {
+            "" => Ok(None),
+            text => key_type.parse(text).map(Some),
+        };
+
+        let start = parse_bound(start)?;
+        let end = match (parse_bound(end)?, inclusive) {
+            (Some(key), true) => Bound::Included(key),
+            (Some(key), false) => Bound::Excluded(key),
+            (None, false) => Bound::Unbounded,
+            (None, true) => return Err("a range ending in ..= needs an end key".to_string()),
+        };
+        Ok(Self { start, end })
+    }
+}
+
+impl RangeBounds<Key> for KeyRange {
+    fn start_bound(&self) -> Bound<&Key> {
+        match &self.start {
+            Some(key) => Bound::Included(key),
+            None => Bound::Unbounded,
+        }
+    }
+
+    fn end_bound(&self) -> Bound<&Key> {
+        self.end.as_ref()
+    }
+}
+
+impl fmt::Display for KeyRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(start) = &self.start {
+            write!(f, "{start}")?;
+        }
+        match &self.end {
+            Bound::Included(end) => write!(f, "..={end}"),
+            Bound::Excluded(end) => write!(f, "..{end}"),
+            Bound::Unbounded => write!(f, ".."),
+        }
+    }
+}
+
+/// One line of a script file: `insert 10 ten`, `get 5`, `delete 5`,
+/// `scan 10..=40`, `clear`, `degree 3` or `keys String`.
+#[derive(Clone, Debug, PartialEq)]
+enum Operation {
+    Insert { key: Key, value: String },
+    Get { key: Key },
+    Delete { key: Key },
+    Scan(KeyRange),
+    Clear,
+    MinDegree(usize),
+    KeyType(KeyType),
+}
+
+impl Operation {
+    /// Whether the operation only reads the tree.
+    fn is_read_only(&self) -> bool {
+        matches!(self, Operation::Get { .. } | Operation::Scan(_))
+    }
+
+    /// Parse one script line, reading keys as `key_type`. Blank lines and `#`
+    /// comments give `None`.
+    fn parse(line: &str, key_type: KeyType) -> Result<Option<Self>, String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let parse_key = |text: &str| key_type.parse(text);
+
+        let operation = match command {
+            "insert" => {
+                // Everything after the key is the value, spaces included.
+                let (key, value) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                Operation::Insert {
+                    key: parse_key(key)?,
+                    value: value.trim().to_string(),
+                }
+            }
+            "get" => Operation::Get {
+                key: parse_key(rest)?,
+            },
+            "delete" => Operation::Delete {
+                key: parse_key(rest)?,
+            },
+            "scan" => Operation::Scan(KeyRange::parse(rest, key_type)?),
+            "clear" if rest.is_empty() => Operation::Clear,
+            "degree" => {
+                let min_degree = rest
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|degree| (2..=MAX_MIN_DEGREE).contains(degree))
+                    .ok_or_else(|| {
+                        format!("minimum degree must be a number from 2 to {MAX_MIN_DEGREE}")
+                    })?;
+                Operation::MinDegree(min_degree)
+            }
+            "keys" => Operation::KeyType(KeyType::from_name(rest).ok_or_else(|| {
+                format!("key type must be one of i64, String or f64, not '{rest}'")
+            })?),
+            _ => return Err(format!("unknown command '{line}'")),
+        };
+        Ok(Some(operation))
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Insert { key, value } if value.is_empty() => write!(f, "insert {key}"),
+            Operation::Insert { key, value } => write!(f, "insert {key} {value}"),
+            Operation::Get { key } => write!(f, "get {key}"),
+            Operation::Delete { key } => write!(f, "delete {key}"),
+            Operation::Scan(range) => write!(f, "scan {range}"),
+            Operation::Clear => write!(f, "clear"),
+            Operation::MinDegree(min_degree) => write!(f, "degree {min_degree}"),
+            Operation::KeyType(key_type) => write!(f, "keys {}", key_type.name()),
+        }
+    }
+}
+
+/// How one tree panel is zoomed and panned, and which of its nodes are
+/// collapsed. Nodes are remembered by their stable ids.
+struct TreeView {
+    zoom: f32,
+    /// Where the layout's origin sits, relative to the panel's top-left corner.
+    pan: Vec2,
+    /// Set by Fit to view and applied on the next frame, once the size of the
+    /// panel is known.
+    fit_pending: bool,
+    collapsed: HashSet<usize>,
+}
+
+impl Default for TreeView {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            pan: Vec2::splat(CANVAS_PADDING),
+            fit_pending: false,
+            collapsed: HashSet::new(),
+        }
+    }
+}
+
+impl TreeView {
+    /// Where a layout box ends up inside a panel whose top-left corner is at
+    /// `canvas_min`.
+    fn to_screen(&self, rect: layout::Rect, canvas_min: Pos2) -> Rect {
+        Rect::from_min_size(
+            canvas_min + self.pan + Vec2::new(rect.x, rect.y) * self.zoom,
+            Vec2::new(rect.width, rect.height) * self.zoom,
+        )
+    }
+
+    /// Zoom and center so that a tree of `tree_size` fills `canvas_size`.
+    fn fit(&mut self, tree_size: Vec2, canvas_size: Vec2) {
+        let room = canvas_size - Vec2::splat(CANVAS_PADDING * 2.0);
+        self.zoom = (room.x / tree_size.x)
+            .min(room.y / tree_size.y)
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+        self.pan = (canvas_size - tree_size * self.zoom) / 2.0;
+    }
+
+    /// Change the zoom by `factor`, keeping the point under `anchor` in place.
+    fn zoom_around(&mut self, factor: f32, anchor: Vec2) {
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.pan = anchor - (anchor - self.pan) * (zoom / self.zoom);
+        self.zoom = zoom;
+    }
+}
+
+/// Extra emphasis drawn on top of the tree.
+#[derive(Default)]
+struct Highlights<'a> {
+    /// Node the current playback step is about.
+    focus: Option<usize>,
+    /// Nodes visited by a search, from the root down.
+    search: &'a [SearchStep],
+    /// Steps of a range scan revealed so far.
+    scan: &'a [RangeStep],
+}
+
+/// The steps of one insert, recorded as snapshots that can be played back.
+struct Playback {
+    frames: Vec<Frame>,
+    current: usize,
+    playing: bool,
+    /// Time at which `current` was first shown, used for both the frame timer
+    /// and the movement animation.
+    shown_at: f64,
+}
+
+struct Frame {
+    snapshot: BTreeSnapshot,
+    caption: String,
+    /// Stable id of the node this step is about.
+    highlight: Option<usize>,
+}
+
+impl Playback {
+    fn frame(&self) -> &Frame {
+        &self.frames[self.current]
+    }
+
+    fn is_finished(&self) -> bool {
+        self.current + 1 == self.frames.len()
+    }
+
+    fn advance(&mut self, now: f64) {
+        if !self.is_finished() {
+            self.current += 1;
+            self.shown_at = now;
+        }
+    }
+
+    /// The frame to animate from and how far along the movement is, or
+    /// `None` once the nodes have settled.
+    fn transition(&self, now: f64) -> Option<(&BTreeSnapshot, f32)> {
+        let progress = (now - self.shown_at) / MOVE_SECONDS;
+        if self.current == 0 || progress >= 1.0 {
+            return None;
+        }
+        Some((&self.frames[self.current - 1].snapshot, progress as f32))
+    }
+}
+
+/// The in-order walk of a range scan, revealed one step at a time.
+struct Scan {
+    steps: Vec<RangeStep>,
+    /// Number of steps revealed so far.
+    shown: usize,
+    playing: bool,
+    /// Time at which the latest step was revealed.
+    shown_at: f64,
+}
+
+impl Scan {
+    fn visible(&self) -> &[RangeStep] {
+        &self.steps[..self.shown]
+    }
+
+    fn is_finished(&self) -> bool {
+        self.shown == self.steps.len()
+    }
+
+    fn advance(&mut self, now: f64) {
+        if !self.is_finished() {
+            self.shown += 1;
+            self.shown_at = now;
+        }
+    }
 }
 
 impl Default for BTreeVisualizerApp {
     fn default() -> Self {
+        let recorder = BTreeRecorder::new();
+        let mut tree = BTree::new(STARTING_MIN_DEGREE);
+        tree.set_observer(recorder.clone());
+        let history = VecDeque::from([HistoryEntry {
+            label: "Start with an empty tree".to_string(),
+            tree: tree.clone(),
+            key_type: STARTING_KEY_TYPE,
+            log_len: 0,
+        }]);
+
         Self {
-            tree: BTree::new(2),
+            tree,
+            key_type: STARTING_KEY_TYPE,
+            recorder,
             insert_key: String::new(),
             insert_value: String::new(),
             get_key: String::new(),
+            delete_key: String::new(),
             status: "Insert a key/value pair to start building the tree.".to_string(),
+            animate_inserts: true,
+            playback: None,
+            search: Vec::new(),
+            scan_range: "10..=40".to_string(),
+            scan: None,
+            comparison: None,
+            comparison_degree: 3,
+            main_view: TreeView::default(),
+            comparison_view: TreeView::default(),
+            bulk_range: "0..100 5".to_string(),
+            random_count: 20,
+            random_seed: 1,
+            bulk_list: String::new(),
+            history,
+            history_index: 0,
+            log: Vec::new(),
+            script_path: "session.btree".to_string(),
+            export_path: "btree.svg".to_string(),
         }
     }
 }
 
+impl BTreeVisualizerApp {
+    /// Add `operation` to the log. If some changes were undone, they and the
+    /// operations behind them are dropped first, as in a text editor.
+    ///
+    /// Lookups and scans leave the tree as it is, so they keep the undone
+    /// changes around for Redo. They go into the log right after the
+    /// operations of the current history entry, which keeps the log in the
+    /// order the operations were performed.
+    fn log_operation(&mut self, operation: Operation) {
+        if operation.is_read_only() {
+            let at = if self.history_index + 1 == self.history.len() {
+                self.log.len()
+            } else {
+                self.history[self.history_index].log_len
+            };
+            self.log.insert(at, operation);
+            for entry in self.history.range_mut(self.history_index..) {
+                if entry.log_len >= at {
+                    entry.log_len += 1;
+                }
+            }
+            return;
+        }
+        if self.history_index + 1 < self.history.len() {
+            self.history.truncate(self.history_index + 1);
+            self.log.truncate(self.history[self.history_index].log_len);
+        }
+        self.log.push(operation);
+    }
+
+    /// Remember the current tree as a new history entry. The oldest entries
+    /// are dropped once there are more than `MAX_HISTORY` of them or they
+    /// hold more than `MAX_HISTORY_KEYS` keys together. The newest entry is
+    /// always kept.
+    fn record(&mut self, label: String) {
+        self.history.push_back(HistoryEntry {
+            label,
+            tree: self.tree.clone(),
+            key_type: self.key_type,
+            log_len: self.log.len(),
+        });
+        let mut stored_keys: usize = self.history.iter().map(|entry| entry.tree.len()).sum();
+        while self.history.len() > 1
+            && (self.history.len() > MAX_HISTORY || stored_keys > MAX_HISTORY_KEYS)
+        {
+            let dropped = self.history.pop_front().unwrap();
+            stored_keys -= dropped.tree.len();
+        }
+        self.history_index = self.history.len() - 1;
+    }
+
+    /// Put the tree back to how it was at history entry `index`.
+    fn jump_to(&mut self, index: usize) {
+        self.history_index = index;
+        // Cloning a tree leaves its observer behind, so attach it again.
+        self.tree = self.history[index].tree.clone();
+        self.tree.set_observer(self.recorder.clone());
+        self.key_type = self.history[index].key_type;
+        if let Some(comparison) = &mut self.comparison {
+            *comparison = rebuild(&self.tree, comparison.min_degree());
+            self.comparison_view.collapsed.clear();
+        }
+        self.playback = None;
+        self.search.clear();
+        self.scan = None;
+        self.status = format!("Back at: {}.", self.history[index].label);
+    }
+
+    fn undo(&mut self) {
+        if self.history_index > 0 {
+            self.jump_to(self.history_index - 1);
+        }
+    }
+
+    fn redo(&mut self) {
+        if self.history_index + 1 < self.history.len() {
+            self.jump_to(self.history_index + 1);
+        }
+    }
+
+    /// Insert one key. With `animate_at` set, the steps of the insert are
+    /// played back starting at that time.
+    fn insert(&mut self, key: Key, value: String, animate_at: Option<f64>) {
+        self.log_operation(Operation::Insert {
+            key: key.clone(),
+            value: value.clone(),
+        });
+        self.recorder.clear();
+        self.search.clear();
+        self.scan = None;
+        if let Some(comparison) = &mut self.comparison {
+            comparison.insert(key.clone(), value.clone());
+        }
+        let previous = match animate_at {
+            Some(now) => {
+                let (previous, frames) = record_insert(&mut self.tree, key.clone(), value.clone());
+                self.playback = Some(Playback {
+                    frames,
+                    current: 0,
+                    playing: true,
+                    shown_at: now,
+                });
+                previous
+            }
+            None => {
+                self.playback = None;
+                self.tree.insert(key.clone(), value.clone())
+            }
+        };
+        self.record(format!("Insert {key}"));
+        self.status = match previous {
+            Some(old_value) => {
+                format!("Updated key {key}. Previous value: {old_value}. New value: {value}.")
+            }
+            None => format!("Inserted key {key} with value {value}."),
+        };
+    }
+
+    fn get(&mut self, key: Key) {
+        self.log_operation(Operation::Get { key: key.clone() });
+        self.playback = None;
+        self.scan = None;
+        self.search = self.tree.search_path(&key);
+        let visited = self.search.len();
+        self.status = match self.tree.get(&key) {
+            Some(value) => {
+                format!("Key {key} maps to value {value}. The search visited {visited} node(s).")
+            }
+            None => format!(
+                "Key {key} is not present in the tree. The search visited {visited} node(s) before reaching a leaf."
+            ),
+        };
+    }
+
+    /// Walk the keys in `range` in order. With `animate_at` set, the walk is
+    /// revealed one step at a time starting at that time.
+    fn scan(&mut self, range: KeyRange, animate_at: Option<f64>) {
+        self.log_operation(Operation::Scan(range.clone()));
+        self.playback = None;
+        self.search.clear();
+        self.scan = None;
+        let steps = self.tree.range_path(range.clone());
+
+        let mut entered = 0;
+        let mut returned = 0;
+        let mut stopped = false;
+        for step in &steps {
+            match step {
+                RangeStep::Enter { .. } => entered += 1,
+                RangeStep::Yield { .. } => returned += 1,
+                RangeStep::Stop { .. } => stopped = true,
+            }
+        }
+        self.status = format!(
+            "Scan {range} returned {returned} key(s) and entered {entered} of {} node(s) on the way.",
+            count_nodes(&self.tree.snapshot())
+        );
+        if stopped {
+            self.status
+                .push_str(" It stopped at the first key past the end of the range.");
+        }
+
+        self.scan = Some(Scan {
+            shown: if animate_at.is_some() {
+                steps.len().min(1)
+            } else {
+                steps.len()
+            },
+            playing: animate_at.is_some(),
+            shown_at: animate_at.unwrap_or_default(),
+            steps,
+        });
+    }
+
+    fn delete(&mut self, key: Key) {
+        self.playback = None;
+        self.search.clear();
+        self.scan = None;
+        // Drop anything recorded by earlier operations so only the steps of
+        // this delete are described.
+        self.recorder.clear();
+        if let Some(comparison) = &mut self.comparison {
+            comparison.remove(&key);
+        }
+        self.status = match self.tree.remove(&key) {
+            Some(value) => {
+                // A miss changes nothing, so only an actual removal is logged
+                // and drops the undone changes.
+                self.log_operation(Operation::Delete { key: key.clone() });
+                self.record(format!("Delete {key}"));
+                let mut status = format!("Deleted key {key} with value {value}.");
+                let steps = describe_rebalancing(&self.recorder.take_events());
+                if steps.is_empty() {
+                    status.push_str(" The leaf kept enough keys, so nothing moved.");
+                }
+                for step in steps {
+                    status.push(' ');
+                    status.push_str(&step);
+                }
+                status
+            }
+            None => format!("Key {key} is not present in the tree."),
+        };
+    }
+
+    /// Rebuild the main tree at `min_degree`, keeping its keys and values.
+    fn set_min_degree(&mut self, min_degree: usize) {
+        self.log_operation(Operation::MinDegree(min_degree));
+        self.tree = rebuild(&self.tree, min_degree);
+        self.main_view.collapsed.clear();
+        self.tree.set_observer(self.recorder.clone());
+        self.record(format!("Rebuild with minimum degree {min_degree}"));
+        self.playback = None;
+        self.search.clear();
+        self.scan = None;
+        self.status = format!(
+            "Rebuilt the tree with minimum degree {min_degree} from its {} key(s).",
+            self.tree.len()
+        );
+    }
+
+    /// Insert every key in `keys`, using the key itself as the value, and
+    /// report how many were new and how often the root split.
+    fn insert_many(&mut self, keys: Vec<Key>, source: &str) {
+        self.playback = None;
+        self.search.clear();
+        self.scan = None;
+        self.recorder.clear();
+
+        let mut added = 0;
+        for key in &keys {
+            self.log_operation(Operation::Insert {
+                key: key.clone(),
+                value: key.to_string(),
+            });
+            if self.tree.insert(key.clone(), key.to_string()).is_none() {
+                added += 1;
+            }
+            if let Some(comparison) = &mut self.comparison {
+                comparison.insert(key.clone(), key.to_string());
+            }
+        }
+
+        // A root split is the only way a B-tree grows taller.
+        let root_splits = self
+            .recorder
+            .take_events()
+            .iter()
+            .filter(|event| matches!(event, BTreeEvent::GrowRoot { .. }))
+            .count();
+        self.record(format!("Insert {} key(s) from {source}", keys.len()));
+        self.status = format!(
+            "Inserted {} key(s) from {source}: {added} new, {} updated. The root split {root_splits} time(s).",
+            keys.len(),
+            keys.len() - added
+        );
+    }
+
+    /// Switch to `key_type`, turning every key into its text and parsing
+    /// that as the new type. Keys that do not parse are dropped.
+    fn set_key_type(&mut self, key_type: KeyType) {
+        self.log_operation(Operation::KeyType(key_type));
+        let mut converted = BTree::new(self.tree.min_degree());
+        let mut dropped = 0;
+        for (key, value) in &self.tree {
+            match key_type.parse(&key.to_string()) {
+                Ok(key) => {
+                    converted.insert(key, value.clone());
+                }
+                Err(_) => dropped += 1,
+            }
+        }
+        self.tree = converted;
+        self.tree.set_observer(self.recorder.clone());
+        self.key_type = key_type;
+        self.main_view.collapsed.clear();
+        if let Some(comparison) = &mut self.comparison {
+            *comparison = rebuild(&self.tree, comparison.min_degree());
+            self.comparison_view.collapsed.clear();
+        }
+        self.record(format!("Switch to {} keys", key_type.name()));
+        self.playback = None;
+        self.search.clear();
+        self.scan = None;
+        self.status = format!(
+            "Rebuilt the tree with {} keys from {} key(s).",
+            key_type.name(),
+            self.tree.len()
+        );
+        if dropped > 0 {
+            self.status.push_str(&format!(
+                " Dropped {dropped} key(s) that are not valid {}.",
+                key_type.name()
+            ));
+        }
+    }
+
+    fn clear(&mut self) {
+        self.log_operation(Operation::Clear);
+        self.tree.clear();
+        if let Some(comparison) = &mut self.comparison {
+            comparison.clear();
+        }
+        self.record("Clear".to_string());
+        self.playback = None;
+        self.search.clear();
+        self.scan = None;
+        self.status = "Cleared the tree.".to_string();
+    }
+
+    /// Operations that lead to the tree on screen. Changes that were undone
+    /// are left out.
+    fn current_log(&self) -> &[Operation] {
+        if self.history_index + 1 == self.history.len() {
+            &self.log
+        } else {
+            &self.log[..self.history[self.history_index].log_len]
+        }
+    }
+
+    fn save_script(&mut self) {
+        let mut script = String::from(
+            "# B-tree visualizer script. Replay it with:\n#   cargo run --bin btree_visualizer -- <file>\n",
+        );
+        for operation in self.current_log() {
+            script.push_str(&operation.to_string());
+            script.push('\n');
+        }
+        self.status = match fs::write(&self.script_path, script) {
+            Ok(()) => format!(
+                "Saved {} operation(s) to {}.",
+                self.current_log().len(),
+                self.script_path
+            ),
+            Err(error) => format!("Could not save {}: {error}", self.script_path),
+        };
+    }
+
+    /// Start over from an empty tree and replay the script at `script_path`.
+    /// Every change in it becomes a history entry, so the scenario can be
+    /// stepped through with Undo and Redo.
+    fn load_script(&mut self) {
+        match read_script(&self.script_path) {
+            Ok(operations) => {
+                let count = operations.len();
+                self.replay(operations);
+                self.status = format!(
+                    "Replayed {count} operation(s) from {}. {}",
+                    self.script_path, self.status
+                );
+            }
+            Err(message) => {
+                self.status = format!("Could not load {}: {message}", self.script_path);
+            }
+        }
+    }
+
+    fn replay(&mut self, operations: Vec<Operation>) {
+        self.tree = BTree::new(STARTING_MIN_DEGREE);
+        self.key_type = STARTING_KEY_TYPE;
+        self.main_view.collapsed.clear();
+        self.tree.set_observer(self.recorder.clone());
+        if let Some(comparison) = &mut self.comparison {
+            comparison.clear();
+        }
+        self.log.clear();
+        self.history = VecDeque::from([HistoryEntry {
+            label: "Start with an empty tree".to_string(),
+            tree: self.tree.clone(),
+            key_type: self.key_type,
+            log_len: 0,
+        }]);
+        self.history_index = 0;
+
+        for operation in operations {
+            match operation {
+                Operation::Insert { key, value } => self.insert(key, value, None),
+                Operation::Get { key } => self.get(key),
+                Operation::Delete { key } => self.delete(key),
+                Operation::Scan(range) => self.scan(range, None),
+                Operation::Clear => self.clear(),
+                Operation::MinDegree(min_degree) => self.set_min_degree(min_degree),
+                Operation::KeyType(key_type) => self.set_key_type(key_type),
+            }
+        }
+    }
+}
+
+/// Replay `script` onto a fresh tree and export the result, without a window.
+fn export_headless(script: Option<&str>, exports: &[String]) -> Result<(), String> {
+    let mut app = BTreeVisualizerApp::default();
+    if let Some(path) = script {
+        let operations = read_script(path).map_err(|message| format!("{path}: {message}"))?;
+        app.replay(operations);
+    }
+
+    let snapshot = app.tree.snapshot();
+    for path in exports {
+        export_tree(&snapshot, path)?;
+        println!("Wrote {path}");
+    }
+    Ok(())
+}
+
+/// Read and parse a whole script, so that a mistake on any line stops the
+/// load before the tree is touched.
+fn read_script(path: &str) -> Result<Vec<Operation>, String> {
+    let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    parse_script(&text)
+}
+
+/// The operations of a script, one per line.
+fn parse_script(text: &str) -> Result<Vec<Operation>, String> {
+    let mut operations = Vec::new();
+    // Keys are read as whatever type the script last switched to.
+    let mut key_type = STARTING_KEY_TYPE;
+    for (number, line) in text.lines().enumerate() {
+        match Operation::parse(line, key_type) {
+            Ok(Some(operation)) => {
+                if let Operation::KeyType(new_type) = operation {
+                    key_type = new_type;
+                }
+                operations.push(operation);
+            }
+            Ok(None) => {}
+            Err(message) => return Err(format!("line {}: {message}", number + 1)),
+        }
+    }
+    Ok(operations)
+}
+
+/// Keys described by `start..end step` or `start..=end step`. The step is
+/// optional and defaults to 1.
+fn parse_range(text: &str) -> Result<Vec<i64>, String> {
+    let mut parts = text.split_whitespace();
+    let range = parts
+        .next()
+        .ok_or("Range must look like start..end step.")?;
+    let step = match parts.next() {
+        Some(step) => step
+            .parse::<usize>()
+            .ok()
+            .filter(|&step| step > 0)
+            .ok_or("Range step must be a positive integer.")?,
+        None => 1,
+    };
+    if parts.next().is_some() {
+        return Err("Range must look like start..end step.".to_string());
+    }
+
+    let (start, end, inclusive) = if let Some((start, end)) = range.split_once("..=") {
+        (start, end, true)
+    } else if let Some((start, end)) = range.split_once("..") {
+        (start, end, false)
+    } else {
+        return Err("Range must look like start..end step.".to_string());
+    };
+    let (Ok(start), Ok(end)) = (start.parse::<i64>(), end.parse::<i64>()) else {
+        return Err("Range bounds must be whole numbers.".to_string());
+    };
+
+    let keys: Vec<i64> = if inclusive {
+        (start..=end)
+            .step_by(step)
+            .take(MAX_BULK_KEYS + 1)
+            .collect()
+    } else {
+        (start..end).step_by(step).take(MAX_BULK_KEYS + 1).collect()
+    };
+    check_bulk_size(keys)
+}
+
+/// Keys of type `key_type` from a comma-separated list such as `5, 12, 7`.
+fn parse_list(text: &str, key_type: KeyType) -> Result<Vec<Key>, String> {
+    let keys = text
+        .split(',')
+        .map(str::trim)
+        .filter(|piece| !piece.is_empty())
+        .map(|piece| key_type.parse(piece))
+        .collect::<Result<Vec<_>, _>>()?;
+    check_bulk_size(keys)
+}
+
+fn check_bulk_size<T>(keys: Vec<T>) -> Result<Vec<T>, String> {
+    if keys.is_empty() {
+        Err("There are no keys to insert.".to_string())
+    } else if keys.len() > MAX_BULK_KEYS {
+        Err(format!(
+            "At most {MAX_BULK_KEYS} keys can be inserted at once."
+        ))
+    } else {
+        Ok(keys)
+    }
+}
+
+/// `count` pseudo-random keys below `10 * count`, so some repeat. The same
+/// seed always gives the same keys.
+fn random_keys(count: usize, seed: u64) -> Vec<i64> {
+    // SplitMix64: tiny, and good enough for picking demo keys.
+    let mut state = seed;
+    let mut next = move || {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    };
+    let bound = (count as u64 * 10).max(1);
+    (0..count).map(|_| (next() % bound) as i64).collect()
+}
+
+/// A new tree with minimum degree `min_degree` holding the entries of `tree`.
+fn rebuild(tree: &BTree<Key, String>, min_degree: usize) -> BTree<Key, String> {
+    let mut rebuilt = BTree::new(min_degree);
+    rebuilt.extend(tree.iter().map(|(key, value)| (key.clone(), value.clone())));
+    rebuilt
+}
+
 impl eframe::App for BTreeVisualizerApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         TopBottomPanel::top("controls").show(ctx, |ui| {
             ui.heading("B-Tree Visualizer");
-            ui.label(format!(
-                "Minimum degree: {} | Keys stored: {}",
-                self.tree.min_degree(),
-                self.tree.len()
-            ));
+            ui.label(format!("Keys stored: {}", self.tree.len()));
             ui.separator();
             controls(ui, self);
         });
 
+        SidePanel::right("history").show(ctx, |ui| history_panel(ui, self));
+
+        let now = ctx.input(|input| input.time);
+        if let Some(playback) = &mut self.playback {
+            if playback.playing && now - playback.shown_at >= FRAME_SECONDS {
+                playback.advance(now);
+                playback.playing = !playback.is_finished();
+            }
+            if playback.playing || playback.transition(now).is_some() {
+                ctx.request_repaint();
+            }
+        }
+        if let Some(scan) = &mut self.scan {
+            if scan.playing && now - scan.shown_at >= SCAN_STEP_SECONDS {
+                scan.advance(now);
+                scan.playing = !scan.is_finished();
+            }
+            if scan.playing {
+                ctx.request_repaint();
+            }
+        }
+
         CentralPanel::default().show(ctx, |ui| {
-            ui.label(&self.status);
+            let live_snapshot = self.tree.snapshot();
+            // The step a range scan has just revealed, while it is still going.
+            let scan_step = self
+                .scan
+                .as_ref()
+                .filter(|scan| !scan.is_finished())
+                .and_then(|scan| scan.visible().last());
+            match (&self.playback, scan_step) {
+                (Some(playback), _) => ui.label(format!(
+                    "Step {}/{}: {}",
+                    playback.current + 1,
+                    playback.frames.len(),
+                    playback.frame().caption
+                )),
+                (None, Some(step)) => {
+                    let scan = self.scan.as_ref().unwrap();
+                    ui.label(format!(
+                        "Scan step {}/{}: {}",
+                        scan.shown,
+                        scan.steps.len(),
+                        describe_scan_step(*step, &live_snapshot)
+                    ))
+                }
+                (None, None) => ui.label(&self.status),
+            };
+            ui.horizontal(|ui| {
+                if ui.button("Fit to view").clicked() {
+                    self.main_view.fit_pending = true;
+                    self.comparison_view.fit_pending = true;
+                }
+                if ui.button("Actual size").clicked() {
+                    self.main_view = TreeView {
+                        collapsed: std::mem::take(&mut self.main_view.collapsed),
+                        ..TreeView::default()
+                    };
+                    self.comparison_view = TreeView {
+                        collapsed: std::mem::take(&mut self.comparison_view.collapsed),
+                        ..TreeView::default()
+                    };
+                }
+                if ui.button("Expand all").clicked() {
+                    self.main_view.collapsed.clear();
+                    self.comparison_view.collapsed.clear();
+                }
+                ui.label(format!(
+                    "Zoom {:.0}%. Scroll to zoom, drag to pan, click a node to collapse or expand it.",
+                    self.main_view.zoom * 100.0
+                ));
+            });
             ui.separator();
 
-            let snapshot = self.tree.snapshot();
-            ScrollArea::both()
-                .auto_shrink([false, false])
-                .show(ui, |ui| {
-                    render_tree(ui, &snapshot);
-                });
+            let (snapshot, highlights, transition) = match &self.playback {
+                Some(playback) => (
+                    &playback.frame().snapshot,
+                    Highlights {
+                        focus: playback.frame().highlight,
+                        ..Highlights::default()
+                    },
+                    playback.transition(now),
+                ),
+                None => {
+                    let highlights = Highlights {
+                        focus: scan_step.map(|step| scan_step_node(*step)),
+                        search: &self.search,
+                        scan: self.scan.as_ref().map_or(&[], Scan::visible),
+                    };
+                    (&live_snapshot, highlights, None)
+                }
+            };
+            match &self.comparison {
+                Some(comparison) => {
+                    let comparison_snapshot = comparison.snapshot();
+                    ui.columns(2, |columns| {
+                        columns[0].label(shape_summary(snapshot));
+                        render_tree(
+                            &mut columns[0],
+                            snapshot,
+                            &highlights,
+                            transition,
+                            &mut self.main_view,
+                        );
+                        columns[1].label(shape_summary(&comparison_snapshot));
+                        render_tree(
+                            &mut columns[1],
+                            &comparison_snapshot,
+                            &Highlights::default(),
+                            None,
+                            &mut self.comparison_view,
+                        );
+                    });
+                }
+                None => {
+                    render_tree(ui, snapshot, &highlights, transition, &mut self.main_view);
+                }
+            }
         });
     }
 }
 
+/// Undo/Redo buttons above the list of recorded changes. Clicking an entry
+/// jumps straight to it.
+fn history_panel(ui: &mut Ui, app: &mut BTreeVisualizerApp) {
+    ui.heading("History");
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(app.history_index > 0, egui::Button::new("Undo"))
+            .clicked()
+        {
+            app.undo();
+        }
+        if ui
+            .add_enabled(
+                app.history_index + 1 < app.history.len(),
+                egui::Button::new("Redo"),
+            )
+            .clicked()
+        {
+            app.redo();
+        }
+    });
+    ui.separator();
+
+    let mut clicked = None;
+    ScrollArea::vertical()
+        .auto_shrink([false, false])
+        .show(ui, |ui| {
+            for (index, entry) in app.history.iter().enumerate() {
+                let label = format!("{}. {}", index + 1, entry.label);
+                if ui
+                    .selectable_label(index == app.history_index, label)
+                    .clicked()
+                {
+                    clicked = Some(index);
+                }
+            }
+        });
+    if let Some(index) = clicked {
+        app.jump_to(index);
+    }
+}
+
+/// One line describing the size and shape of the tree in `snapshot`.
+fn shape_summary(snapshot: &BTreeSnapshot) -> String {
+    let mut height = 0;
+    let mut node_count = 0;
+    let mut stack: Vec<&BTreeNodeSnapshot> = snapshot.root.iter().collect();
+    while let Some(node) = stack.pop() {
+        height = height.max(node.depth + 1);
+        node_count += 1;
+        stack.extend(&node.children);
+    }
+    format!(
+        "Minimum degree {}: height {height}, {node_count} node(s)",
+        snapshot.min_degree
+    )
+}
+
 fn controls(ui: &mut Ui, app: &mut BTreeVisualizerApp) {
+    ui.horizontal(|ui| {
+        ui.label("Minimum degree");
+        let mut min_degree = app.tree.min_degree();
+        if ui
+            .add(DragValue::new(&mut min_degree).range(2..=MAX_MIN_DEGREE))
+            .changed()
+            && min_degree != app.tree.min_degree()
+        {
+            app.set_min_degree(min_degree);
+        }
+
+        let mut side_by_side = app.comparison.is_some();
+        if ui
+            .checkbox(&mut side_by_side, "Compare with degree")
+            .changed()
+        {
+            app.comparison = side_by_side.then(|| rebuild(&app.tree, app.comparison_degree));
+            app.comparison_view = TreeView::default();
+        }
+        if ui
+            .add(DragValue::new(&mut app.comparison_degree).range(2..=MAX_MIN_DEGREE))
+            .changed()
+            && let Some(comparison) = &mut app.comparison
+        {
+            *comparison = rebuild(comparison, app.comparison_degree);
+            app.comparison_view.collapsed.clear();
+        }
+
+        ui.separator();
+        let mut key_type = app.key_type;
+        egui::ComboBox::from_label("Key type")
+            .selected_text(key_type.name())
+            .show_ui(ui, |ui| {
+                for option in KeyType::ALL {
+                    ui.selectable_value(&mut key_type, option, option.name());
+                }
+            });
+        if key_type != app.key_type {
+            app.set_key_type(key_type);
+        }
+    });
+
     ui.horizontal(|ui| {
         ui.label("Insert key");
         ui.add(TextEdit::singleline(&mut app.insert_key).desired_width(80.0));
@@ -74,250 +1354,1238 @@ fn controls(ui: &mut Ui, app: &mut BTreeVisualizerApp) {
         ui.add(TextEdit::singleline(&mut app.insert_value).desired_width(160.0));
 
         if ui.button("Insert").clicked() {
-            match app.insert_key.trim().parse::<i32>() {
+            match app.key_type.parse(app.insert_key.trim()) {
                 Ok(key) => {
                     let value = app.insert_value.trim().to_string();
-                    let previous = app.tree.insert(key, value.clone());
-                    app.status = match previous {
-                        Some(old_value) => format!(
-                            "Updated key {key}. Previous value: {old_value}. New value: {value}."
-                        ),
-                        None => format!("Inserted key {key} with value {value}."),
-                    };
+                    let animate_at = app.animate_inserts.then(|| ui.input(|input| input.time));
+                    app.insert(key, value, animate_at);
                     app.insert_key.clear();
                     app.insert_value.clear();
                 }
-                Err(_) => {
-                    app.status = "Insert key must be a valid i32.".to_string();
-                }
+                Err(message) => app.status = format!("Insert key: {message}."),
             }
         }
+
+        ui.checkbox(&mut app.animate_inserts, "Animate inserts");
     });
 
+    let mut finished = false;
+    if let Some(playback) = &mut app.playback {
+        let now = ui.input(|input| input.time);
+        ui.horizontal(|ui| {
+            ui.label("Playback");
+            if ui
+                .add_enabled(!playback.is_finished(), egui::Button::new("Step"))
+                .clicked()
+            {
+                playback.playing = false;
+                playback.advance(now);
+            }
+            if ui
+                .add_enabled(
+                    !playback.playing && !playback.is_finished(),
+                    egui::Button::new("Play"),
+                )
+                .clicked()
+            {
+                playback.playing = true;
+                playback.shown_at = now;
+            }
+            if ui
+                .add_enabled(playback.playing, egui::Button::new("Pause"))
+                .clicked()
+            {
+                playback.playing = false;
+            }
+            if ui.button("Finish").clicked() {
+                finished = true;
+            }
+        });
+    }
+    if finished {
+        app.playback = None;
+    }
+
     ui.horizontal(|ui| {
         ui.label("Get key");
         ui.add(TextEdit::singleline(&mut app.get_key).desired_width(80.0));
 
         if ui.button("Get").clicked() {
-            match app.get_key.trim().parse::<i32>() {
-                Ok(key) => match app.tree.get(&key) {
-                    Some(value) => {
-                        app.status = format!("Key {key} maps to value {value}.");
-                    }
-                    None => {
-                        app.status = format!("Key {key} is not present in the tree.");
-                    }
-                },
-                Err(_) => {
-                    app.status = "Get key must be a valid i32.".to_string();
+            match app.key_type.parse(app.get_key.trim()) {
+                Ok(key) => app.get(key),
+                Err(message) => {
+                    app.search.clear();
+                    app.status = format!("Get key: {message}.");
+                }
+            }
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Scan range");
+        ui.add(TextEdit::singleline(&mut app.scan_range).desired_width(80.0));
+        if ui.button("Scan").clicked() {
+            match KeyRange::parse(&app.scan_range, app.key_type) {
+                Ok(range) => app.scan(range, Some(ui.input(|input| input.time))),
+                Err(message) => {
+                    app.scan = None;
+                    app.status = format!("Scan range: {message}.");
+                }
+            }
+        }
+
+        if let Some(scan) = &mut app.scan
+            && !scan.is_finished()
+        {
+            let now = ui.input(|input| input.time);
+            ui.separator();
+            if ui.button("Step").clicked() {
+                scan.playing = false;
+                scan.advance(now);
+            }
+            if ui
+                .add_enabled(!scan.playing, egui::Button::new("Play"))
+                .clicked()
+            {
+                scan.playing = true;
+                scan.shown_at = now;
+            }
+            if ui
+                .add_enabled(scan.playing, egui::Button::new("Pause"))
+                .clicked()
+            {
+                scan.playing = false;
+            }
+            if ui.button("Finish").clicked() {
+                scan.playing = false;
+                scan.shown = scan.steps.len();
+            }
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Delete key");
+        ui.add(TextEdit::singleline(&mut app.delete_key).desired_width(80.0));
+
+        if ui.button("Delete").clicked() {
+            match app.key_type.parse(app.delete_key.trim()) {
+                Ok(key) => {
+                    app.delete(key);
+                    app.delete_key.clear();
+                }
+                Err(message) => app.status = format!("Delete key: {message}."),
+            }
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Range");
+        ui.add(TextEdit::singleline(&mut app.bulk_range).desired_width(120.0));
+        if ui.button("Insert range").clicked() {
+            match parse_range(&app.bulk_range) {
+                Ok(numbers) => {
+                    let keys = numbers
+                        .into_iter()
+                        .map(|number| app.key_type.key_from_int(number))
+                        .collect();
+                    let source = format!("range {}", app.bulk_range.trim());
+                    app.insert_many(keys, &source);
+                }
+                Err(message) => app.status = message,
+            }
+        }
+
+        ui.separator();
+        ui.label("Random keys");
+        ui.add(DragValue::new(&mut app.random_count).range(1..=MAX_BULK_KEYS));
+        ui.label("Seed");
+        ui.add(DragValue::new(&mut app.random_seed));
+        if ui.button("Insert random").clicked() {
+            let keys = random_keys(app.random_count, app.random_seed)
+                .into_iter()
+                .map(|number| app.key_type.key_from_int(number))
+                .collect();
+            let source = format!("seed {}", app.random_seed);
+            app.insert_many(keys, &source);
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Key list");
+        ui.add(
+            TextEdit::singleline(&mut app.bulk_list)
+                .hint_text("5, 12, 7")
+                .desired_width(240.0),
+        );
+        if ui.button("Insert list").clicked() {
+            match parse_list(&app.bulk_list, app.key_type) {
+                Ok(keys) => {
+                    app.insert_many(keys, "the list");
+                    app.bulk_list.clear();
                 }
+                Err(message) => app.status = message,
             }
         }
+
+        ui.separator();
+        if ui.button("Clear").clicked() {
+            app.clear();
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Script file");
+        ui.add(TextEdit::singleline(&mut app.script_path).desired_width(240.0));
+        if ui.button("Save").clicked() {
+            app.save_script();
+        }
+        if ui.button("Load").clicked() {
+            app.load_script();
+        }
+
+        ui.separator();
+        ui.label("Export to");
+        ui.add(TextEdit::singleline(&mut app.export_path).desired_width(160.0));
+        if ui.button("Export").clicked() {
+            app.status = match export_tree(&app.tree.snapshot(), &app.export_path) {
+                Ok(()) => format!("Exported the tree to {}.", app.export_path),
+                Err(message) => message,
+            };
+        }
     });
 }
 
-fn render_tree(ui: &mut Ui, snapshot: &BTreeSnapshot) {
-    let Some(root) = &snapshot.root else {
-        ui.label("The tree is empty.");
-        return;
+/// Insert into `tree` and capture one frame per step of the insert: each
+/// descent, each split of a full child followed by its median moving up, and
+/// finally the insert into the leaf.
+fn record_insert(
+    tree: &mut BTree<Key, String>,
+    key: Key,
+    value: String,
+) -> (Option<String>, Vec<Frame>) {
+    let before = tree.snapshot();
+    let root_id = before.root.as_ref().map(|root| root.id);
+    let mut frames = vec![Frame {
+        snapshot: before,
+        caption: format!("Inserting {key}, starting at the root."),
+        highlight: root_id,
+    }];
+    // Child indices from the root to the node the insert is currently at.
+    let mut path = Vec::new();
+
+    let previous = tree.insert_traced(key, value, |tree, event| {
+        let snapshot = tree.snapshot();
+        let (caption, highlight) = match event {
+            BTreeEvent::GrowRoot { height } => {
+                path.clear();
+                (
+                    format!(
+                        "The root is full, so a new empty root is added above it. The tree now has {height} levels."
+                    ),
+                    node_id_at(&snapshot, &path),
+                )
+            }
+            BTreeEvent::SplitChild {
+                depth,
+                child_index,
+                median,
+            } => {
+                path.truncate(depth);
+
+                // Show the full child once more before it is split.
+                let before = frames.last().unwrap().snapshot.clone();
+                let mut child_path = path.clone();
+                child_path.push(child_index);
+                let full_child = node_id_at(&before, &child_path);
+                frames.push(Frame {
+                    snapshot: before,
+                    caption: format!(
+                        "Child {child_index} is full with {} keys, so it is split around its median {median}.",
+                        2 * tree.min_degree() - 1
+                    ),
+                    highlight: full_child,
+                });
+
+                (
+                    format!("The median {median} moves up into the parent."),
+                    node_id_at(&snapshot, &path),
+                )
+            }
+            BTreeEvent::Descend { depth, child_index } => {
+                path.truncate(depth);
+                path.push(child_index);
+                (
+                    format!("Descend into child {child_index}."),
+                    node_id_at(&snapshot, &path),
+                )
+            }
+            BTreeEvent::InsertIntoLeaf { index, key, .. } => (
+                format!("Insert {key} into the leaf at position {index}."),
+                node_id_at(&snapshot, &path),
+            ),
+            BTreeEvent::ReplaceValue { key, .. } => (
+                format!("Key {key} is already stored here, so only its value is replaced."),
+                node_id_at(&snapshot, &path),
+            ),
+            _ => return,
+        };
+
+        frames.push(Frame {
+            snapshot,
+            caption,
+            highlight,
+        });
+    });
+
+    (previous, frames)
+}
+
+/// Id of the node reached by following `path` down from the root.
+fn node_id_at(snapshot: &BTreeSnapshot, path: &[usize]) -> Option<usize> {
+    let mut node = snapshot.root.as_ref()?;
+    for &index in path {
+        node = node.children.get(index)?;
+    }
+    Some(node.id)
+}
+
+fn find_node(snapshot: &BTreeSnapshot, id: usize) -> Option<&BTreeNodeSnapshot> {
+    let mut stack: Vec<&BTreeNodeSnapshot> = snapshot.root.iter().collect();
+    while let Some(node) = stack.pop() {
+        if node.id == id {
+            return Some(node);
+        }
+        stack.extend(&node.children);
+    }
+    None
+}
+
+fn count_nodes(snapshot: &BTreeSnapshot) -> usize {
+    let mut count = 0;
+    let mut stack: Vec<&BTreeNodeSnapshot> = snapshot.root.iter().collect();
+    while let Some(node) = stack.pop() {
+        count += 1;
+        stack.extend(&node.children);
+    }
+    count
+}
+
+/// Stable id of the node a range scan step is about.
+fn scan_step_node(step: RangeStep) -> usize {
+    match step {
+        RangeStep::Enter { node_id, .. }
+        | RangeStep::Yield { node_id, .. }
+        | RangeStep::Stop { node_id, .. } => node_id,
+    }
+}
+
+/// One sentence saying what a range scan step did, for the caption above the
+/// tree.
+fn describe_scan_step(step: RangeStep, snapshot: &BTreeSnapshot) -> String {
+    let Some(node) = find_node(snapshot, scan_step_node(step)) else {
+        return String::new();
     };
+    match step {
+        RangeStep::Enter { depth, .. } => {
+            format!(
+                "Enter the node [{}] at depth {depth}.",
+                node.keys.join(", ")
+            )
+        }
+        RangeStep::Yield { index, .. } if node.is_leaf => {
+            format!("Return key {} from the leaf.", node.keys[index])
+        }
+        RangeStep::Yield { index, .. } => format!(
+            "Return key {} from an internal node, then go down into the child to its right.",
+            node.keys[index]
+        ),
+        RangeStep::Stop { index, .. } => format!(
+            "Key {} is past the end of the range, so the scan stops.",
+            node.keys[index]
+        ),
+    }
+}
 
-    let measured = measure_node(root);
-    let canvas_size = Vec2::new(
-        measured.subtree_width + CANVAS_PADDING * 2.0,
-        measured.subtree_height + CANVAS_PADDING * 2.0,
-    );
-    let (rect, _) = ui.allocate_exact_size(canvas_size, Sense::hover());
-    let painter = ui.painter_at(rect);
-
-    let mut nodes = Vec::new();
-    let mut edges = Vec::new();
-    place_node(
-        root,
-        Pos2::new(rect.min.x + CANVAS_PADDING, rect.min.y + CANVAS_PADDING),
-        &mut nodes,
-        &mut edges,
-    );
+/// Turn the events recorded during a delete into sentences for the status
+/// line. Only the steps that change the shape of the tree are mentioned.
+fn describe_rebalancing(events: &[BTreeEvent<Key>]) -> Vec<String> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            BTreeEvent::ReplaceWithPredecessor {
+                depth, predecessor, ..
+            } => Some(format!(
+                "The key sat in an internal node at depth {depth}, so its predecessor {predecessor} took its place."
+            )),
+            BTreeEvent::BorrowFromLeft {
+                depth,
+                child_index,
+                separator,
+            } => Some(format!(
+                "Child {child_index} of the node at depth {depth} borrowed from its left sibling; {separator} moved up as the new separator."
+            )),
+            BTreeEvent::BorrowFromRight {
+                depth,
+                child_index,
+                separator,
+            } => Some(format!(
+                "Child {child_index} of the node at depth {depth} borrowed from its right sibling; {separator} moved up as the new separator."
+            )),
+            BTreeEvent::MergeChildren {
+                depth,
+                child_index,
+                separator,
+            } => Some(format!(
+                "Children {child_index} and {} of the node at depth {depth} merged around {separator}.",
+                child_index + 1
+            )),
+            BTreeEvent::ShrinkRoot { height } => Some(format!(
+                "The root became empty and the tree shrank to {height} level(s)."
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Draw `snapshot` into the rest of the panel, zoomed and panned by `view`.
+/// While `transition` is set, nodes that also appear in the previous snapshot
+/// are drawn part of the way between their old and new places, matched by
+/// their stable id.
+fn render_tree(
+    ui: &mut Ui,
+    snapshot: &BTreeSnapshot,
+    highlights: &Highlights,
+    transition: Option<(&BTreeSnapshot, f32)>,
+    view: &mut TreeView,
+) {
+    let (canvas, response) = ui.allocate_exact_size(ui.available_size(), Sense::click_and_drag());
+    let painter = ui.painter_at(canvas);
+    if snapshot.root.is_none() {
+        painter.text(
+            canvas.center(),
+            Align2::CENTER_CENTER,
+            "The tree is empty.",
+            FontId::proportional(14.0),
+            ui.visuals().text_color(),
+        );
+        return;
+    }
 
-    for edge in edges {
-        painter.line_segment(
-            [edge.from, edge.to],
-            Stroke::new(1.5, Color32::from_rgb(90, 90, 90)),
+    let config = LayoutConfig::default();
+    let tree_layout = TreeLayout::with_collapsed(snapshot, &config, &view.collapsed);
+    if view.fit_pending {
+        view.fit(
+            Vec2::new(tree_layout.width, tree_layout.height),
+            canvas.size(),
         );
+        view.fit_pending = false;
+    }
+    if response.dragged() {
+        view.pan += response.drag_delta();
+    }
+    if let Some(pointer) = response.hover_pos() {
+        let scroll = ui.input(|input| input.smooth_scroll_delta.y);
+        if scroll != 0.0 {
+            view.zoom_around((scroll * ZOOM_PER_SCROLL).exp(), pointer - canvas.min);
+        }
+    }
+
+    let mut rects: HashMap<usize, Rect> = tree_layout
+        .nodes
+        .iter()
+        .map(|node| (node.id, view.to_screen(node.rect, canvas.min)))
+        .collect();
+
+    if let Some((previous, progress)) = transition {
+        let previous = TreeLayout::with_collapsed(previous, &config, &view.collapsed);
+        // Ease out so nodes slow down as they arrive.
+        let t = 1.0 - (1.0 - progress).powi(3);
+        for node in &previous.nodes {
+            if let Some(rect) = rects.get_mut(&node.id) {
+                let from = view.to_screen(node.rect, canvas.min);
+                *rect = Rect::from_min_max(from.min.lerp(rect.min, t), from.max.lerp(rect.max, t));
+            }
+        }
+    }
+
+    // Edges the search went down, as (parent id, child id) pairs.
+    let searched_edges: Vec<(usize, usize)> = highlights
+        .search
+        .windows(2)
+        .map(|pair| (pair[0].node_id, pair[1].node_id))
+        .collect();
+    let search_hit = highlights
+        .search
+        .last()
+        .is_some_and(|step| step.comparison.is_ok());
+    // Edges the range scan went down. Each node it enters hangs below the
+    // node it last entered one level up.
+    let mut scanned_edges = Vec::new();
+    let mut entered = HashSet::new();
+    let mut scan_path: Vec<usize> = Vec::new();
+    for step in highlights.scan {
+        if let RangeStep::Enter { node_id, depth } = *step {
+            scan_path.truncate(depth);
+            if let Some(&parent_id) = scan_path.last() {
+                scanned_edges.push((parent_id, node_id));
+            }
+            scan_path.push(node_id);
+            entered.insert(node_id);
+        }
+    }
+
+    for edge in &tree_layout.edges {
+        let parent = rects[&edge.parent_id];
+        let child = rects[&edge.child_id];
+        let stroke = if searched_edges.contains(&(edge.parent_id, edge.child_id)) {
+            Stroke::new(3.0, Color32::from_rgb(52, 120, 200))
+        } else if scanned_edges.contains(&(edge.parent_id, edge.child_id)) {
+            Stroke::new(3.0, Color32::from_rgb(130, 80, 190))
+        } else {
+            Stroke::new(1.5, Color32::from_rgb(90, 90, 90))
+        };
+        painter.line_segment([parent.center_bottom(), child.center_top()], stroke);
     }
 
-    for node in nodes {
-        let response = ui.interact(node.rect, Id::new(("btree-node", node.id)), Sense::hover());
-        let fill = if response.hovered() {
+    let font = FontId::monospace(14.0 * view.zoom);
+    let mut toggled = None;
+    for node in &tree_layout.nodes {
+        let node_rect = rects[&node.id];
+        if !canvas.intersects(node_rect) {
+            continue;
+        }
+        let response = ui.interact(
+            node_rect.intersect(canvas),
+            Id::new(("btree-node", node.id)),
+            Sense::click(),
+        );
+        let search_step = highlights
+            .search
+            .iter()
+            .find(|step| step.node_id == node.id);
+        let is_last_step = highlights
+            .search
+            .last()
+            .is_some_and(|step| step.node_id == node.id);
+
+        let fill = if highlights.focus == Some(node.id) {
+            Color32::from_rgb(255, 210, 140)
+        } else if is_last_step && search_hit {
+            Color32::from_rgb(204, 238, 204)
+        } else if search_step.is_some() {
+            Color32::from_rgb(210, 228, 250)
+        } else if entered.contains(&node.id) {
+            Color32::from_rgb(228, 218, 248)
+        } else if response.hovered() {
             Color32::from_rgb(255, 244, 214)
         } else {
             Color32::from_rgb(234, 239, 244)
         };
+        let outline = if is_last_step && !search_hit {
+            Stroke::new(2.5, Color32::from_rgb(200, 60, 60))
+        } else {
+            Stroke::new(1.0, Color32::from_rgb(52, 73, 94))
+        };
 
+        // A collapsed node shows its label in the top half of its box and a
+        // summary of the hidden subtree in the bottom half.
+        let label_rect = match node.collapsed {
+            Some(_) => node_rect.split_top_bottom_at_fraction(0.5).0,
+            None => node_rect,
+        };
         painter.rect(
-            node.rect,
-            8.0,
+            node_rect,
+            8.0 * view.zoom,
             fill,
-            Stroke::new(1.0, Color32::from_rgb(52, 73, 94)),
+            outline,
             StrokeKind::Outside,
         );
         painter.text(
-            node.rect.center(),
+            label_rect.center(),
             Align2::CENTER_CENTER,
-            node.label,
-            FontId::monospace(14.0),
+            &node.label,
+            font.clone(),
             Color32::from_rgb(30, 30, 30),
         );
+        if let Some(summary) = &node.collapsed {
+            painter.line_segment(
+                [label_rect.left_bottom(), label_rect.right_bottom()],
+                Stroke::new(1.0, Color32::from_rgb(180, 190, 200)),
+            );
+            painter.text(
+                Pos2::new(
+                    node_rect.center().x,
+                    label_rect.max.y + label_rect.height() / 2.0,
+                ),
+                Align2::CENTER_CENTER,
+                summary.to_string(),
+                font.clone(),
+                Color32::from_rgb(90, 100, 110),
+            );
+        }
+
+        if let Some(step) = search_step {
+            mark_comparison(&painter, node, label_rect, &font, step.comparison);
+        }
+        for step in highlights.scan {
+            match *step {
+                RangeStep::Yield { node_id, index } if node_id == node.id => mark_key(
+                    &painter,
+                    node,
+                    label_rect,
+                    &font,
+                    index,
+                    Color32::from_rgb(40, 150, 60),
+                ),
+                RangeStep::Stop { node_id, index } if node_id == node.id => mark_key(
+                    &painter,
+                    node,
+                    label_rect,
+                    &font,
+                    index,
+                    Color32::from_rgb(200, 60, 60),
+                ),
+                _ => {}
+            }
+        }
 
+        let can_toggle = !node.is_leaf;
+        if can_toggle && response.clicked() {
+            toggled = Some(node.id);
+        }
         if response.hovered() {
             response.on_hover_ui(|ui| {
-                ui.label(format!("Depth: {}", node.metadata.depth));
-                ui.label(format!("Leaf: {}", node.metadata.is_leaf));
-                ui.label(format!("Keys in node: {}", node.metadata.key_count));
-                ui.label(format!("Children: {}", node.metadata.child_count));
+                ui.label(format!("Depth: {}", node.depth));
+                ui.label(format!("Leaf: {}", node.is_leaf));
+                ui.label(format!("Keys in node: {}", node.key_count));
+                ui.label(format!("Children: {}", node.child_count));
+                if let Some(summary) = &node.collapsed {
+                    ui.label(format!(
+                        "Hidden: {} key(s) in {} node(s), from {} to {}",
+                        summary.key_count, summary.node_count, summary.first_key, summary.last_key
+                    ));
+                    ui.label("Click to expand.");
+                } else if can_toggle {
+                    ui.label("Click to collapse.");
+                }
             });
         }
     }
+
+    if let Some(id) = toggled
+        && !view.collapsed.remove(&id)
+    {
+        view.collapsed.insert(id);
+    }
 }
 
-#[derive(Clone)]
-struct MeasuredNode {
-    subtree_width: f32,
-    subtree_height: f32,
-    node_width: f32,
-    child_offsets: Vec<f32>,
-    children: Vec<MeasuredNode>,
+/// Show where the search landed inside a node: a box around the matching
+/// key, or a bar in the gap between the keys the searched key falls between.
+fn mark_comparison(
+    painter: &Painter,
+    node: &LayoutNode,
+    node_rect: Rect,
+    font: &FontId,
+    comparison: Result<usize, usize>,
+) {
+    let index = match comparison {
+        Ok(index) => {
+            mark_key(
+                painter,
+                node,
+                node_rect,
+                font,
+                index,
+                Color32::from_rgb(40, 150, 60),
+            );
+            return;
+        }
+        Err(index) => index,
+    };
+    let label_left = entry_left(painter, node, node_rect, font, 0);
+    let x = if index == node.entries.len() {
+        label_left + text_width(painter, font, node.label.clone()) + 4.0
+    } else if index == 0 {
+        label_left - 4.0
+    } else {
+        // Middle of the " | " separator before entry `index`.
+        entry_left(painter, node, node_rect, font, index)
+            - text_width(painter, font, " | ".to_string()) / 2.0
+    };
+    painter.line_segment(
+        [
+            Pos2::new(x, node_rect.min.y + 4.0),
+            Pos2::new(x, node_rect.max.y - 4.0),
+        ],
+        Stroke::new(3.0, Color32::from_rgb(52, 120, 200)),
+    );
 }
 
-#[derive(Clone)]
-struct DrawNode {
-    id: usize,
-    rect: Rect,
-    label: String,
-    metadata: NodeMetadata,
+/// Draw a box of `color` around entry `index` of a node.
+fn mark_key(
+    painter: &Painter,
+    node: &LayoutNode,
+    node_rect: Rect,
+    font: &FontId,
+    index: usize,
+    color: Color32,
+) {
+    let left = entry_left(painter, node, node_rect, font, index);
+    let right = left + text_width(painter, font, node.entries[index].clone());
+    let key_rect = Rect::from_min_max(
+        Pos2::new(left - 3.0, node_rect.min.y + 5.0),
+        Pos2::new(right + 3.0, node_rect.max.y - 5.0),
+    );
+    painter.rect_stroke(key_rect, 4.0, Stroke::new(2.0, color), StrokeKind::Outside);
+}
+
+/// Left edge of entry `index` in a node's centered label, measured by laying
+/// out the label up to it.
+fn entry_left(
+    painter: &Painter,
+    node: &LayoutNode,
+    node_rect: Rect,
+    font: &FontId,
+    index: usize,
+) -> f32 {
+    let label_left = node_rect.center().x - text_width(painter, font, node.label.clone()) / 2.0;
+    if index == 0 {
+        label_left
+    } else {
+        label_left
+            + text_width(
+                painter,
+                font,
+                format!("{} | ", node.entries[..index].join(" | ")),
+            )
+    }
 }
 
-#[derive(Clone)]
-struct NodeMetadata {
-    depth: usize,
-    is_leaf: bool,
-    key_count: usize,
-    child_count: usize,
+fn text_width(painter: &Painter, font: &FontId, text: String) -> f32 {
+    painter
+        .layout_no_wrap(text, font.clone(), Color32::BLACK)
+        .size()
+        .x
 }
 
-#[derive(Clone)]
-struct Edge {
-    from: Pos2,
-    to: Pos2,
+/// Move a layout box, which starts at `(0, 0)`, to `origin` on screen.
+fn to_screen(rect: layout::Rect, origin: Pos2) -> Rect {
+    Rect::from_min_size(
+        Pos2::new(origin.x + rect.x, origin.y + rect.y),
+        Vec2::new(rect.width, rect.height),
+    )
 }
 
-fn measure_node(node: &BTreeNodeSnapshot) -> MeasuredNode {
-    let label = node_label(node);
-    let node_width = label.chars().count() as f32 * 8.0 + NODE_HORIZONTAL_PADDING * 2.0;
+/// Write the tree in `snapshot` to `path`, as SVG or PNG depending on the
+/// extension.
+fn export_tree(snapshot: &BTreeSnapshot, path: &str) -> Result<(), String> {
+    let svg = tree_to_svg(snapshot);
+    let extension = std::path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let bytes = match extension.as_deref() {
+        Some("svg") => svg.into_bytes(),
+        Some("png") => svg_to_png(&svg)?,
+        _ => return Err(format!("Cannot export to {path}: use a .svg or .png file.")),
+    };
+    fs::write(path, bytes).map_err(|error| format!("Could not write {path}: {error}"))
+}
 
-    if node.children.is_empty() {
-        return MeasuredNode {
-            subtree_width: node_width,
-            subtree_height: NODE_HEIGHT,
-            node_width,
-            child_offsets: Vec::new(),
-            children: Vec::new(),
-        };
+/// Draw the tree as an SVG document, using the same layout and colors as
+/// `render_tree`. The image is sized to fit the whole tree.
+fn tree_to_svg(snapshot: &BTreeSnapshot) -> String {
+    if snapshot.root.is_none() {
+        return svg_document(
+            Vec2::new(240.0, 80.0),
+            r#"<text x="120" y="40" font-family="monospace" font-size="14" text-anchor="middle" dominant-baseline="central" fill="rgb(30,30,30)">The tree is empty.</text>"#,
+        );
     }
 
-    let children: Vec<MeasuredNode> = node.children.iter().map(measure_node).collect();
-    let total_children_width = children
+    let tree_layout = TreeLayout::new(snapshot, &LayoutConfig::default());
+    let size = Vec2::new(tree_layout.width, tree_layout.height) + Vec2::splat(CANVAS_PADDING * 2.0);
+    let origin = Pos2::new(CANVAS_PADDING, CANVAS_PADDING);
+    let rects: HashMap<usize, Rect> = tree_layout
+        .nodes
         .iter()
-        .map(|child| child.subtree_width)
-        .sum::<f32>()
-        + HORIZONTAL_GAP * (children.len().saturating_sub(1) as f32);
-    let subtree_width = node_width.max(total_children_width);
-
-    let mut child_offsets = Vec::with_capacity(children.len());
-    let mut current_x = (subtree_width - total_children_width) / 2.0;
-    let mut max_child_height: f32 = 0.0;
+        .map(|node| (node.id, to_screen(node.rect, origin)))
+        .collect();
 
-    for child in &children {
-        child_offsets.push(current_x);
-        current_x += child.subtree_width + HORIZONTAL_GAP;
-        max_child_height = max_child_height.max(child.subtree_height);
+    let mut body = String::new();
+    for edge in &tree_layout.edges {
+        let from = rects[&edge.parent_id].center_bottom();
+        let to = rects[&edge.child_id].center_top();
+        body.push_str(&format!(
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="rgb(90,90,90)" stroke-width="1.5"/>"#,
+            from.x, from.y, to.x, to.y
+        ));
+        body.push('\n');
     }
-
-    MeasuredNode {
-        subtree_width,
-        subtree_height: NODE_HEIGHT + VERTICAL_GAP + max_child_height,
-        node_width,
-        child_offsets,
-        children,
+    for node in &tree_layout.nodes {
+        let rect = rects[&node.id];
+        let center = rect.center();
+        body.push_str(&format!(
+            r#"<rect x="{}" y="{}" width="{}" height="{}" rx="8" fill="rgb(234,239,244)" stroke="rgb(52,73,94)"/>"#,
+            rect.min.x,
+            rect.min.y,
+            rect.width(),
+            rect.height()
+        ));
+        body.push('\n');
+        body.push_str(&format!(
+            r#"<text x="{}" y="{}" font-family="monospace" font-size="14" text-anchor="middle" dominant-baseline="central" fill="rgb(30,30,30)">{}</text>"#,
+            center.x,
+            center.y,
+            escape_xml(&node.label)
+        ));
+        body.push('\n');
     }
+
+    svg_document(size, &body)
 }
 
-fn place_node(
-    node: &BTreeNodeSnapshot,
-    origin: Pos2,
-    nodes: &mut Vec<DrawNode>,
-    edges: &mut Vec<Edge>,
-) {
-    let measured = measure_node(node);
-    place_node_with_measurement(node, &measured, origin, nodes, edges);
+fn svg_document(size: Vec2, body: &str) -> String {
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">
+<rect width="100%" height="100%" fill="white"/>
+{body}
+</svg>
+"#,
+        w = size.x.ceil(),
+        h = size.y.ceil()
+    )
 }
 
-fn place_node_with_measurement(
-    node: &BTreeNodeSnapshot,
-    measured: &MeasuredNode,
-    origin: Pos2,
-    nodes: &mut Vec<DrawNode>,
-    edges: &mut Vec<Edge>,
-) {
-    let node_min = Pos2::new(
-        origin.x + (measured.subtree_width - measured.node_width) / 2.0,
-        origin.y,
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Rasterize an SVG document produced by `tree_to_svg`.
+fn svg_to_png(svg: &str) -> Result<Vec<u8>, String> {
+    use resvg::{tiny_skia, usvg};
+
+    let mut options = usvg::Options::default();
+    let fonts = options.fontdb_mut();
+    fonts.load_system_fonts();
+    // usvg maps `monospace` to a fixed font name that may not be installed,
+    // so point it at whichever monospaced font the system has.
+    let monospace = fonts
+        .faces()
+        .find(|face| face.monospaced)
+        .and_then(|face| face.families.first())
+        .map(|(family, _)| family.clone());
+    if let Some(family) = monospace {
+        fonts.set_monospace_family(family);
+    }
+    let tree = usvg::Tree::from_str(svg, &options).map_err(|error| error.to_string())?;
+
+    let size = tree
+        .size()
+        .to_int_size()
+        .scale_by(PNG_SCALE)
+        .ok_or("The tree is too large to export as PNG.")?;
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or("The tree is too large to export as PNG.")?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(PNG_SCALE, PNG_SCALE),
+        &mut pixmap.as_mut(),
     );
-    let node_rect = Rect::from_min_size(node_min, Vec2::new(measured.node_width, NODE_HEIGHT));
-
-    nodes.push(DrawNode {
-        id: node.id,
-        rect: node_rect,
-        label: node_label(node),
-        metadata: NodeMetadata {
-            depth: node.depth,
-            is_leaf: node.is_leaf,
-            key_count: node.key_count,
-            child_count: node.child_count,
-        },
-    });
+    pixmap.encode_png().map_err(|error| error.to_string())
+}
 
-    for ((child, child_measured), child_offset) in node
-        .children
-        .iter()
-        .zip(measured.children.iter())
-        .zip(measured.child_offsets.iter())
-    {
-        let child_origin = Pos2::new(
-            origin.x + *child_offset,
-            origin.y + NODE_HEIGHT + VERTICAL_GAP,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(key: i64) -> Operation {
+        Operation::Insert {
+            key: Key::Integer(key),
+            value: key.to_string(),
+        }
+    }
+
+    #[test]
+    fn lookups_keep_the_redo_history() {
+        let mut app = BTreeVisualizerApp::default();
+        app.insert(Key::Integer(1), "1".to_string(), None);
+        app.insert(Key::Integer(2), "2".to_string(), None);
+        app.undo();
+
+        app.get(Key::Integer(1));
+        app.scan(KeyRange::parse("..", KeyType::Integer).unwrap(), None);
+        assert_eq!(app.history.len(), 3);
+        assert_eq!(app.current_log().len(), 3);
+
+        app.redo();
+        assert_eq!(app.tree.len(), 2);
+        assert_eq!(
+            app.current_log(),
+            [
+                insert(1),
+                Operation::Get {
+                    key: Key::Integer(1)
+                },
+                Operation::Scan(KeyRange::parse("..", KeyType::Integer).unwrap()),
+                insert(2),
+            ]
+        );
+
+        // A change made after undoing still drops the undone one.
+        app.undo();
+        app.insert(Key::Integer(3), "3".to_string(), None);
+        assert_eq!(app.history.len(), 3);
+        assert_eq!(app.current_log()[3], insert(3));
+        assert_eq!(app.current_log().len(), 4);
+    }
+
+    #[test]
+    fn history_drops_the_oldest_entries() {
+        let mut app = BTreeVisualizerApp::default();
+        for key in 0..MAX_HISTORY as i64 + 20 {
+            app.insert(Key::Integer(key), key.to_string(), None);
+        }
+
+        assert_eq!(app.history.len(), MAX_HISTORY);
+        assert_eq!(app.history_index, MAX_HISTORY - 1);
+        assert_eq!(app.history[0].label, "Insert 20");
+
+        for _ in 0..MAX_HISTORY {
+            app.undo();
+        }
+        assert_eq!(app.tree.len(), 21);
+        assert_eq!(app.current_log().len(), 21);
+    }
+
+    fn args(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn command_line_is_parsed() {
+        assert_eq!(
+            args(&[]),
+            Ok(Some(Args {
+                script: None,
+                exports: vec![]
+            }))
         );
-        let child_rect_min_x =
-            child_origin.x + (child_measured.subtree_width - child_measured.node_width) / 2.0;
-        let child_top = Pos2::new(
-            child_rect_min_x + child_measured.node_width / 2.0,
-            child_origin.y,
+        assert_eq!(
+            args(&["run.btree", "--export", "a.svg", "--export", "b.png"]),
+            Ok(Some(Args {
+                script: Some("run.btree".to_string()),
+                exports: vec!["a.svg".to_string(), "b.png".to_string()]
+            }))
         );
+        assert_eq!(args(&["run.btree", "--help"]), Ok(None));
+        assert_eq!(args(&["-h"]), Ok(None));
 
-        edges.push(Edge {
-            from: Pos2::new(node_rect.center().x, node_rect.max.y),
-            to: child_top,
-        });
+        assert!(args(&["--export"]).is_err());
+        assert!(args(&["--exprot", "a.svg"]).is_err());
+        assert!(args(&["-"]).is_err());
+        assert!(args(&["one.btree", "two.btree"]).is_err());
+    }
+
+    #[test]
+    fn scripts_round_trip() {
+        let script = "\
+insert 10 ten
+insert 5
+get 10
+delete 5
+scan 10..=40
+scan ..
+clear
+degree 3
+keys String
+insert apple a red fruit
+scan apple..banana
+keys f64
+insert -0 negative zero
+insert NaN
+get 2.5
+scan ..=1.5
+";
+        let operations = parse_script(script).unwrap();
+        assert_eq!(operations.len(), 16);
+        assert_eq!(
+            operations[9],
+            Operation::Insert {
+                key: Key::Text("apple".to_string()),
+                value: "a red fruit".to_string()
+            }
+        );
 
-        place_node_with_measurement(child, child_measured, child_origin, nodes, edges);
+        let written: String = operations
+            .iter()
+            .map(|operation| format!("{operation}\n"))
+            .collect();
+        assert_eq!(written, script);
+        assert_eq!(parse_script(&written).unwrap(), operations);
     }
-}
 
-fn node_label(node: &BTreeNodeSnapshot) -> String {
-    node.keys
-        .iter()
-        .zip(node.values.iter())
-        .map(|(key, value)| format!("{key}:{value}"))
-        .collect::<Vec<_>>()
-        .join(" | ")
+    #[test]
+    fn scripts_skip_blank_lines_and_comments() {
+        let operations =
+            parse_script("# a comment\n\n   \n  insert 1 one  \n\t# indented\n").unwrap();
+        assert_eq!(
+            operations,
+            [Operation::Insert {
+                key: Key::Integer(1),
+                value: "one".to_string()
+            }]
+        );
+        assert_eq!(parse_script(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn script_errors_name_the_line() {
+        let error = |script: &str| parse_script(script).unwrap_err();
+
+        assert_eq!(
+            error("insert 1\nfrobnicate 2"),
+            "line 2: unknown command 'frobnicate 2'"
+        );
+        assert_eq!(error("get ten"), "line 1: 'ten' is not a valid i64");
+        assert_eq!(error("insert"), "line 1: '' is not a valid i64");
+        assert_eq!(error("clear now"), "line 1: unknown command 'clear now'");
+        assert!(error("degree 1").starts_with("line 1: minimum degree"));
+        assert!(error("keys u8").starts_with("line 1: key type must be one of"));
+        // Keys are read as the type the script last switched to.
+        assert_eq!(
+            error("keys f64\nget 1\nget one"),
+            "line 3: 'one' is not a valid f64"
+        );
+        assert_eq!(
+            error("keys String\ndelete"),
+            "line 2: '' is not a valid String key: use one word"
+        );
+    }
+
+    #[test]
+    fn bulk_ranges_are_parsed() {
+        assert_eq!(parse_range("0..5"), Ok(vec![0, 1, 2, 3, 4]));
+        assert_eq!(parse_range("0..=10 5"), Ok(vec![0, 5, 10]));
+        assert_eq!(parse_range("  -3..3   2 "), Ok(vec![-3, -1, 1]));
+        assert_eq!(
+            parse_range(&format!("1..={MAX_BULK_KEYS}")).map(|keys| keys.len()),
+            Ok(MAX_BULK_KEYS)
+        );
+
+        for text in ["", "5", "0..5 2 3", "0-5"] {
+            assert_eq!(
+                parse_range(text),
+                Err("Range must look like start..end step.".to_string()),
+                "{text:?}"
+            );
+        }
+        for text in ["0..5 0", "0..5 -1", "0..5 two"] {
+            assert_eq!(
+                parse_range(text),
+                Err("Range step must be a positive integer.".to_string()),
+                "{text:?}"
+            );
+        }
+        assert_eq!(
+            parse_range("a..5"),
+            Err("Range bounds must be whole numbers.".to_string())
+        );
+        assert_eq!(
+            parse_range("5..5"),
+            Err("There are no keys to insert.".to_string())
+        );
+        assert_eq!(
+            parse_range("0..1000000"),
+            Err(format!(
+                "At most {MAX_BULK_KEYS} keys can be inserted at once."
+            ))
+        );
+    }
+
+    #[test]
+    fn bulk_lists_are_parsed() {
+        assert_eq!(
+            parse_list("5, 12,7,,", KeyType::Integer),
+            Ok(vec![Key::Integer(5), Key::Integer(12), Key::Integer(7)])
+        );
+        assert_eq!(
+            parse_list("pear, fig", KeyType::Text),
+            Ok(vec![
+                Key::Text("pear".to_string()),
+                Key::Text("fig".to_string())
+            ])
+        );
+        assert_eq!(
+            parse_list("1.5", KeyType::Float),
+            Ok(vec![Key::Float(TotalF64(1.5))])
+        );
+
+        assert_eq!(
+            parse_list("5, x", KeyType::Integer),
+            Err("'x' is not a valid i64".to_string())
+        );
+        assert_eq!(
+            parse_list("green pear", KeyType::Text),
+            Err("'green pear' is not a valid String key: use one word".to_string())
+        );
+        assert_eq!(
+            parse_list(" , ", KeyType::Integer),
+            Err("There are no keys to insert.".to_string())
+        );
+        let too_many = vec!["1"; MAX_BULK_KEYS + 1].join(",");
+        assert!(parse_list(&too_many, KeyType::Integer).is_err());
+    }
+
+    #[test]
+    fn keys_are_parsed_as_the_key_type() {
+        for key_type in KeyType::ALL {
+            assert_eq!(KeyType::from_name(key_type.name()), Some(key_type));
+        }
+        assert_eq!(KeyType::from_name("I64"), None);
+
+        assert_eq!(KeyType::Integer.parse("-7"), Ok(Key::Integer(-7)));
+        assert_eq!(KeyType::Text.parse("7"), Ok(Key::Text("7".to_string())));
+        assert_eq!(KeyType::Float.parse("7"), Ok(Key::Float(TotalF64(7.0))));
+        assert_eq!(
+            KeyType::Integer.parse("7.5"),
+            Err("'7.5' is not a valid i64".to_string())
+        );
+        assert!(KeyType::Float.parse("seven").is_err());
+        assert!(KeyType::Text.parse("").is_err());
+        assert!(KeyType::Text.parse("a b").is_err());
+
+        assert_eq!(KeyType::Integer.key_from_int(10), Key::Integer(10));
+        assert_eq!(KeyType::Float.key_from_int(10), Key::Float(TotalF64(10.0)));
+        // Text keys made from numbers sort by their digits.
+        assert!(KeyType::Text.key_from_int(10) < KeyType::Text.key_from_int(9));
+    }
+
+    #[test]
+    fn floats_have_a_total_order() {
+        let key = |text: &str| KeyType::Float.parse(text).unwrap();
+        let mut keys: Vec<Key> = ["NaN", "1", "inf", "0", "-0", "-inf", "-1"]
+            .into_iter()
+            .map(key)
+            .collect();
+        keys.sort();
+        let sorted: Vec<String> = keys.iter().map(Key::to_string).collect();
+        assert_eq!(sorted, ["-inf", "-1", "-0", "0", "1", "inf", "NaN"]);
+
+        // NaN equals itself, so it can be found again, and -0 is its own key.
+        assert_eq!(key("NaN"), key("NaN"));
+        assert_ne!(key("-0"), key("0"));
+        let mut tree = BTree::new(2);
+        for text in ["NaN", "0", "-0", "NaN"] {
+            tree.insert(key(text), text.to_string());
+        }
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.get(&key("NaN")), Some(&"NaN".to_string()));
+        assert_eq!(tree.get(&key("-0")), Some(&"-0".to_string()));
+    }
+
+    #[test]
+    fn key_ranges_are_parsed_and_written_back() {
+        let range = |text: &str| KeyRange::parse(text, KeyType::Integer);
+
+        for text in ["10..40", "10..=40", "10..", "..40", "..=40", ".."] {
+            assert_eq!(range(text).unwrap().to_string(), text);
+        }
+        assert_eq!(
+            range(" -5 ..= 5 "),
+            Ok(KeyRange {
+                start: Some(Key::Integer(-5)),
+                end: Bound::Included(Key::Integer(5))
+            })
+        );
+        assert_eq!(
+            KeyRange::parse("a..c", KeyType::Text).unwrap().end,
+            Bound::Excluded(Key::Text("c".to_string()))
+        );
+
+        assert_eq!(
+            range("10"),
+            Err("'10' is not a range such as 10..=40".to_string())
+        );
+        assert_eq!(
+            range("10..="),
+            Err("a range ending in ..= needs an end key".to_string())
+        );
+        assert_eq!(range("x..5"), Err("'x' is not a valid i64".to_string()));
+    }
+
+    #[test]
+    fn scans_return_the_keys_in_range() {
+        let mut app = BTreeVisualizerApp::default();
+        for key in (0..100).step_by(10) {
+            app.insert(Key::Integer(key), key.to_string(), None);
+        }
+
+        let scanned = |app: &BTreeVisualizerApp| -> Vec<String> {
+            let scan = app.scan.as_ref().unwrap();
+            assert!(scan.is_finished());
+            let snapshot = app.tree.snapshot();
+            scan.steps
+                .iter()
+                .filter_map(|step| match *step {
+                    RangeStep::Yield { node_id, index } => {
+                        Some(find_node(&snapshot, node_id).unwrap().keys[index].clone())
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+
+        app.scan(KeyRange::parse("20..=40", KeyType::Integer).unwrap(), None);
+        assert_eq!(scanned(&app), ["20", "30", "40"]);
+        assert!(app.status.starts_with("Scan 20..=40 returned 3 key(s)"));
+
+        app.scan(KeyRange::parse("75..", KeyType::Integer).unwrap(), None);
+        assert_eq!(scanned(&app), ["80", "90"]);
+
+        app.scan(KeyRange::parse("41..50", KeyType::Integer).unwrap(), None);
+        assert!(scanned(&app).is_empty());
+    }
+
+    #[test]
+    fn deleting_a_missing_key_keeps_the_redo_history() {
+        let mut app = BTreeVisualizerApp::default();
+        app.insert(Key::Integer(1), "1".to_string(), None);
+        app.insert(Key::Integer(2), "2".to_string(), None);
+        app.undo();
+
+        app.delete(Key::Integer(7));
+        assert_eq!(app.history.len(), 3);
+        assert_eq!(app.current_log(), [insert(1)]);
+
+        app.redo();
+        assert_eq!(app.current_log(), [insert(1), insert(2)]);
+        app.delete(Key::Integer(1));
+        assert_eq!(
+            app.current_log(),
+            [
+                insert(1),
+                insert(2),
+                Operation::Delete {
+                    key: Key::Integer(1)
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn recorder_only_holds_the_latest_operation() {
+        let mut app = BTreeVisualizerApp::default();
+        for key in 0..50 {
+            app.insert(Key::Integer(key), key.to_string(), None);
+        }
+        app.insert(Key::Integer(50), "50".to_string(), Some(0.0));
+        assert!(app.recorder.events().len() <= 10);
+
+        app.delete(Key::Integer(0));
+        app.get(Key::Integer(1));
+        assert!(app.recorder.events().is_empty());
+    }
 }
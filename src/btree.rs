@@ -1,4 +1,22 @@
-use std::fmt::Display;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Display};
+use std::hash::{Hash, Hasher};
+use std::ops::{Bound, Index, RangeBounds};
+
+mod events;
+mod iter;
+pub mod layout;
+#[cfg(feature = "serde")]
+mod serialization;
+
+pub use events::{BTreeEvent, BTreeObserver, BTreeRecorder};
+pub use iter::{ExtractIf, IntoIter, Iter};
+
+/// Minimum degree used by `BTree::default`. It matches the node size of
+/// `std::collections::BTreeMap`, whose nodes hold up to 11 keys.
+pub const DEFAULT_MIN_DEGREE: usize = 6;
+
+type BoxedObserver<K> = Box<dyn BTreeObserver<K> + Send + Sync>;
 
 /// A small B-tree meant for understanding how insert and get work.
 pub struct BTree<K, V> {
@@ -6,20 +24,38 @@ pub struct BTree<K, V> {
     min_degree: usize,
     root: Option<Node<K, V>>,
     len: usize,
+    /// Id handed to the next node that gets created. See `Node::id`.
+    next_node_id: usize,
+    /// Optional hook that is told about every step the tree takes as it changes.
+    observer: Option<BoxedObserver<K>>,
 }
 
 /// Each node stores multiple keys and values.
 ///
 /// If the node is not a leaf, it also stores child nodes.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct Node<K, V> {
+    /// Stable identity of the node within its tree. It stays the same while
+    /// keys move in and out, so the visualizer can follow a node between
+    /// snapshots. The left half of a split keeps the old id.
+    #[cfg_attr(feature = "serde", serde(default))]
+    id: usize,
     keys: Vec<K>,
     values: Vec<V>,
     children: Vec<Node<K, V>>,
 }
 
+/// Where an entry lives: the child indices leading from the root to its node,
+/// and the index of the entry inside that node.
+struct Position {
+    path: Vec<usize>,
+    index: usize,
+}
+
 /// The visualizer reads from this snapshot instead of the generic tree itself.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BTreeSnapshot {
     pub min_degree: usize,
     pub len: usize,
@@ -27,7 +63,10 @@ pub struct BTreeSnapshot {
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BTreeNodeSnapshot {
+    /// Stable id of the node. The same node keeps its id across snapshots
+    /// taken before and after the tree changes.
     pub id: usize,
     pub depth: usize,
     pub is_leaf: bool,
@@ -38,9 +77,42 @@ pub struct BTreeNodeSnapshot {
     pub children: Vec<BTreeNodeSnapshot>,
 }
 
+/// One node visited while searching for a key. See `BTree::search_path`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchStep {
+    /// Stable id of the node, matching `BTreeNodeSnapshot::id`.
+    pub node_id: usize,
+    pub depth: usize,
+    /// The result of searching this node's keys, in the same form as
+    /// `slice::binary_search`: `Ok(i)` when key `i` matched, `Err(i)` when the
+    /// key belongs before key `i`. On an internal node `Err(i)` means the
+    /// search continued into child `i`; on a leaf it means the key is missing.
+    pub comparison: Result<usize, usize>,
+}
+
+/// One step of the in-order walk a range scan makes. See `BTree::range_path`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RangeStep {
+    /// The scan moved into a node. `node_id` matches `BTreeNodeSnapshot::id`.
+    Enter { node_id: usize, depth: usize },
+    /// Key `index` of the node is inside the range and was returned.
+    Yield { node_id: usize, index: usize },
+    /// Key `index` of the node is past the end of the range, so the scan
+    /// stopped there.
+    Stop { node_id: usize, index: usize },
+}
+
+/// Pass `event` on to the observer, if one is installed.
+fn notify<K>(observer: &mut Option<BoxedObserver<K>>, event: BTreeEvent<&K>) {
+    if let Some(observer) = observer {
+        observer.on_event(event);
+    }
+}
+
 impl<K, V> Node<K, V> {
-    fn new_leaf(key: K, value: V) -> Self {
+    fn new_leaf(id: usize, key: K, value: V) -> Self {
         Self {
+            id,
             keys: vec![key],
             values: vec![value],
             children: Vec::new(),
@@ -68,7 +140,9 @@ impl<K, V> Node<K, V> {
     }
 
     /// Split a full child into two smaller children and move the middle key up.
-    fn split_child(&mut self, child_index: usize, min_degree: usize) {
+    ///
+    /// The right half becomes a new node with id `right_id`.
+    fn split_child(&mut self, child_index: usize, min_degree: usize, right_id: usize) {
         // Temporarily remove the full child from the parent so we can split it
         // into two separate nodes.
         let mut child = self.children.remove(child_index);
@@ -103,6 +177,7 @@ impl<K, V> Node<K, V> {
         self.children.insert(
             child_index + 1,
             Node {
+                id: right_id,
                 keys: right_keys,
                 values: right_values,
                 children: right_children,
@@ -110,79 +185,157 @@ impl<K, V> Node<K, V> {
         );
     }
 
-    /// Insert into a node that is known to have space.
+    /// Number of levels from this node down to its leaves.
+    fn height(&self) -> usize {
+        1 + self.children.first().map_or(0, Node::height)
+    }
+
+    /// Follow `path`, a list of child indices, down from this node.
+    fn descendant(&self, path: &[usize]) -> &Self {
+        path.iter().fold(self, |node, &index| &node.children[index])
+    }
+
+    fn descendant_mut(&mut self, path: &[usize]) -> &mut Self {
+        path.iter()
+            .fold(self, |node, &index| &mut node.children[index])
+    }
+
+    /// Rotate one key from the left sibling of `child_index` through this
+    /// node and into the child.
     ///
-    /// If the key already exists, replace its value and return the old value.
-    fn insert_non_full(&mut self, key: K, value: V, min_degree: usize) -> Option<V>
+    /// The sibling's last key replaces the separator, and the old separator
+    /// becomes the child's first key. For internal nodes the sibling's last
+    /// child moves across with it.
+    fn borrow_from_left(&mut self, child_index: usize) {
+        let left = &mut self.children[child_index - 1];
+        let key = left.keys.pop().unwrap();
+        let value = left.values.pop().unwrap();
+        let grandchild = left.children.pop();
+
+        let key = std::mem::replace(&mut self.keys[child_index - 1], key);
+        let value = std::mem::replace(&mut self.values[child_index - 1], value);
+
+        let child = &mut self.children[child_index];
+        child.keys.insert(0, key);
+        child.values.insert(0, value);
+        if let Some(grandchild) = grandchild {
+            child.children.insert(0, grandchild);
+        }
+    }
+
+    /// Mirror image of `borrow_from_left`, taking the right sibling's first
+    /// key instead.
+    fn borrow_from_right(&mut self, child_index: usize) {
+        let right = &mut self.children[child_index + 1];
+        let key = right.keys.remove(0);
+        let value = right.values.remove(0);
+        let grandchild = (!right.is_leaf()).then(|| right.children.remove(0));
+
+        let key = std::mem::replace(&mut self.keys[child_index], key);
+        let value = std::mem::replace(&mut self.values[child_index], value);
+
+        let child = &mut self.children[child_index];
+        child.keys.push(key);
+        child.values.push(value);
+        if let Some(grandchild) = grandchild {
+            child.children.push(grandchild);
+        }
+    }
+
+    /// Merge child `child_index + 1` into child `child_index`, pulling the
+    /// separator between them down into the merged node.
+    fn merge_children(&mut self, child_index: usize) {
+        let right = self.children.remove(child_index + 1);
+        let separator_key = self.keys.remove(child_index);
+        let separator_value = self.values.remove(child_index);
+
+        let left = &mut self.children[child_index];
+        left.keys.push(separator_key);
+        left.values.push(separator_value);
+        left.keys.extend(right.keys);
+        left.values.extend(right.values);
+        left.children.extend(right.children);
+    }
+
+    /// Walk the keys of this subtree that fall inside `range`, in order, and
+    /// record every step in `steps`. Returns `false` once a key past the end
+    /// of the range was reached, so the caller stops as well.
+    fn range_path<R>(&self, depth: usize, range: &R, steps: &mut Vec<RangeStep>) -> bool
     where
         K: Ord,
+        R: RangeBounds<K>,
     {
-        match self.keys.binary_search(&key) {
-            // key already exists in this node, so we only
-            // replace the old value.
-            Ok(index) => Some(std::mem::replace(&mut self.values[index], value)),
-
-            
-            // This a leaf node, and they key was not found.
-            // The `index` is the exact sorted position where the
-            // new key must be inserted to keep the keys ordered.
-            Err(index) if self.is_leaf() => {
-                self.keys.insert(index, key);
-                self.values.insert(index, value);
-                None
-            }
-
-            // `Err(index)` on an internal node means the key is not stored in
-            // this node, and the search must continue in child `index`.
-            //
-            // Example:
-            // keys = [10, 20, 30]
-            // key  = 25
-            // binary_search returns `Err(2)`, so we continue into child 2,
-            // which is the subtree between 20 and 30.
-            Err(mut index) => {
-                if self.children[index].is_full(min_degree) {
-                    // Before descending, split a full child so we never recurse
-                    // into a node that has no room left.
-                    self.split_child(index, min_degree);
-
-                    // Splitting moved one key up into the current node.
-                    // We now decide whether the new key belongs:
-                    // - to the left child
-                    // - exactly on the promoted key
-                    // - to the right child
-                    if key > self.keys[index] {
-                        index += 1;
-                    } else if key == self.keys[index] {
-                        return Some(std::mem::replace(&mut self.values[index], value));
-                    }
-                }
+        steps.push(RangeStep::Enter {
+            node_id: self.id,
+            depth,
+        });
+        // Keys before `start` are below the range, and so is everything in
+        // the children to their left.
+        let start = match range.start_bound() {
+            Bound::Included(bound) => self.keys.partition_point(|key| key < bound),
+            Bound::Excluded(bound) => self.keys.partition_point(|key| key <= bound),
+            Bound::Unbounded => 0,
+        };
+        // When the start bound is itself a key here, the child before it
+        // only holds smaller keys.
+        let starts_at_key = matches!(
+            range.start_bound(),
+            Bound::Included(bound) if self.keys.get(start) == Some(bound)
+        );
 
-                self.children[index].insert_non_full(key, value, min_degree)
+        for index in start..=self.keys.len() {
+            let skip_child = index == start && starts_at_key;
+            if let Some(child) = self.children.get(index)
+                && !skip_child
+                && !child.range_path(depth + 1, range, steps)
+            {
+                return false;
             }
+            let Some(key) = self.keys.get(index) else {
+                break;
+            };
+            let past_end = match range.end_bound() {
+                Bound::Included(bound) => key > bound,
+                Bound::Excluded(bound) => key >= bound,
+                Bound::Unbounded => false,
+            };
+            if past_end {
+                steps.push(RangeStep::Stop {
+                    node_id: self.id,
+                    index,
+                });
+                return false;
+            }
+            steps.push(RangeStep::Yield {
+                node_id: self.id,
+                index,
+            });
         }
+        true
     }
 
-    fn to_snapshot(&self, depth: usize, next_id: &mut usize) -> BTreeNodeSnapshot
+    fn to_snapshot<FK, FV>(
+        &self,
+        depth: usize,
+        key_fmt: &mut FK,
+        value_fmt: &mut FV,
+    ) -> BTreeNodeSnapshot
     where
-        K: Display,
-        V: Display,
+        FK: FnMut(&K) -> String,
+        FV: FnMut(&V) -> String,
     {
-        let id = *next_id;
-        *next_id += 1;
-
         BTreeNodeSnapshot {
-            id,
+            id: self.id,
             depth,
             is_leaf: self.is_leaf(),
             key_count: self.keys.len(),
             child_count: self.children.len(),
-            keys: self.keys.iter().map(ToString::to_string).collect(),
-            values: self.values.iter().map(ToString::to_string).collect(),
+            keys: self.keys.iter().map(&mut *key_fmt).collect(),
+            values: self.values.iter().map(&mut *value_fmt).collect(),
             children: self
                 .children
                 .iter()
-                .map(|child| child.to_snapshot(depth + 1, next_id))
+                .map(|child| child.to_snapshot(depth + 1, key_fmt, value_fmt))
                 .collect(),
         }
     }
@@ -196,6 +349,8 @@ impl<K, V> BTree<K, V> {
             min_degree,
             root: None,
             len: 0,
+            next_node_id: 0,
+            observer: None,
         }
     }
 
@@ -211,6 +366,20 @@ impl<K, V> BTree<K, V> {
         self.len == 0
     }
 
+    /// Install an observer that is told about every step the tree takes while
+    /// it changes. Any previously installed observer is replaced.
+    pub fn set_observer<O>(&mut self, observer: O)
+    where
+        O: BTreeObserver<K> + Send + Sync + 'static,
+    {
+        self.observer = Some(Box::new(observer));
+    }
+
+    /// Remove the installed observer, if any, and hand it back.
+    pub fn take_observer(&mut self) -> Option<Box<dyn BTreeObserver<K> + Send + Sync>> {
+        self.observer.take()
+    }
+
     pub fn get(&self, key: &K) -> Option<&V>
     where
         K: Ord,
@@ -218,6 +387,70 @@ impl<K, V> BTree<K, V> {
         self.root.as_ref().and_then(|root| root.get(key))
     }
 
+    pub fn contains_key(&self, key: &K) -> bool
+    where
+        K: Ord,
+    {
+        self.get(key).is_some()
+    }
+
+    /// The nodes a lookup of `key` visits, from the root down.
+    ///
+    /// The last step tells whether the key was found: its `comparison` is
+    /// `Ok` for a hit and `Err` for a miss. An empty tree gives an empty path.
+    pub fn search_path(&self, key: &K) -> Vec<SearchStep>
+    where
+        K: Ord,
+    {
+        let mut steps = Vec::new();
+        let mut current = self.root.as_ref();
+
+        while let Some(node) = current {
+            let comparison = node.keys.binary_search(key);
+            steps.push(SearchStep {
+                node_id: node.id,
+                depth: steps.len(),
+                comparison,
+            });
+            current = comparison
+                .err()
+                .and_then(|child_index| node.children.get(child_index));
+        }
+
+        steps
+    }
+
+    /// The steps a scan over `range` takes: every node it enters and every
+    /// key it returns, in order, ending with the first key past the range if
+    /// it reached one.
+    ///
+    /// The scan starts with a lookup of the start bound, so it skips the
+    /// subtrees that lie entirely below the range.
+    pub fn range_path<R>(&self, range: R) -> Vec<RangeStep>
+    where
+        K: Ord,
+        R: RangeBounds<K>,
+    {
+        let mut steps = Vec::new();
+        if let Some(root) = &self.root {
+            root.range_path(0, &range, &mut steps);
+        }
+        steps
+    }
+
+    /// Visit every key/value pair in ascending key order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter::new(self.root.as_ref(), self.len)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+
     /// Insert a key/value pair.
     ///
     /// If the key already exists, its value is replaced and the old value is
@@ -226,37 +459,469 @@ impl<K, V> BTree<K, V> {
     where
         K: Ord,
     {
-        if self.root.is_none() {
-            self.root = Some(Node::new_leaf(key, value));
+        self.insert_traced(
+            key,
+            value,
+            #[inline]
+            |_, _| {},
+        )
+    }
+
+    /// Insert a key/value pair, calling `on_step` after every step.
+    ///
+    /// `on_step` sees the same events as an installed observer, together with
+    /// the tree as it looks right after that step. The visualizer uses this
+    /// to take a snapshot per step and play the insert back frame by frame.
+    ///
+    /// Because `on_step` borrows the whole tree between steps, the walk keeps
+    /// the path of child indices instead of a reference to the current node,
+    /// and follows it down from the root again after each step. That makes an
+    /// insert O(depth²) node visits rather than O(depth); with depth growing
+    /// as the logarithm of the length, the difference stays small.
+    pub fn insert_traced<F>(&mut self, key: K, value: V, mut on_step: F) -> Option<V>
+    where
+        K: Ord,
+        F: FnMut(&Self, BTreeEvent<&K>),
+    {
+        let min_degree = self.min_degree;
+
+        let Some(root) = self.root.as_ref() else {
+            let id = self.allocate_node_id();
+            self.root = Some(Node::new_leaf(id, key, value));
             self.len = 1;
+            self.report(&mut on_step, |tree| BTreeEvent::InsertIntoLeaf {
+                depth: 0,
+                index: 0,
+                key: &tree.root.as_ref().unwrap().keys[0],
+            });
             return None;
-        }
+        };
 
-        if self
-            .root
-            .as_ref()
-            .is_some_and(|root| root.is_full(self.min_degree))
-        {
+        if root.is_full(min_degree) {
             let old_root = self.root.take().unwrap();
+            let height = old_root.height() + 1;
             self.root = Some(Node {
+                id: self.allocate_node_id(),
                 keys: Vec::new(),
                 values: Vec::new(),
                 children: vec![old_root],
             });
-            self.root.as_mut().unwrap().split_child(0, self.min_degree);
+            self.report(&mut on_step, |_| BTreeEvent::GrowRoot { height });
+
+            let right_id = self.allocate_node_id();
+            self.root
+                .as_mut()
+                .unwrap()
+                .split_child(0, min_degree, right_id);
+            self.report(&mut on_step, |tree| BTreeEvent::SplitChild {
+                depth: 0,
+                child_index: 0,
+                median: &tree.root.as_ref().unwrap().keys[0],
+            });
         }
 
-        let result = self
-            .root
-            .as_mut()
-            .unwrap()
-            .insert_non_full(key, value, self.min_degree);
+        // Walk down from the root, keeping track of the child indices taken
+        // so far. Every node we step into is known to have room for one more
+        // key, because full children are split before we enter them.
+        let mut path = Vec::new();
+        loop {
+            let depth = path.len();
+            let node = self.root.as_mut().unwrap().descendant_mut(&path);
 
-        if result.is_none() {
-            self.len += 1;
+            match node.keys.binary_search(&key) {
+                // key already exists in this node, so we only
+                // replace the old value.
+                Ok(index) => {
+                    let old_value = std::mem::replace(&mut node.values[index], value);
+                    self.report(&mut on_step, |tree| BTreeEvent::ReplaceValue {
+                        depth,
+                        index,
+                        key: &tree.node_at(&path).keys[index],
+                    });
+                    return Some(old_value);
+                }
+
+                // This a leaf node, and they key was not found.
+                // The `index` is the exact sorted position where the
+                // new key must be inserted to keep the keys ordered.
+                Err(index) if node.is_leaf() => {
+                    node.keys.insert(index, key);
+                    node.values.insert(index, value);
+                    self.len += 1;
+                    self.report(&mut on_step, |tree| BTreeEvent::InsertIntoLeaf {
+                        depth,
+                        index,
+                        key: &tree.node_at(&path).keys[index],
+                    });
+                    return None;
+                }
+
+                // `Err(index)` on an internal node means the key is not stored in
+                // this node, and the search must continue in child `index`.
+                //
+                // Example:
+                // keys = [10, 20, 30]
+                // key  = 25
+                // binary_search returns `Err(2)`, so we continue into child 2,
+                // which is the subtree between 20 and 30.
+                Err(mut index) => {
+                    if node.children[index].is_full(min_degree) {
+                        // Before descending, split a full child so we never
+                        // step into a node that has no room left.
+                        let right_id = self.allocate_node_id();
+                        let node = self.root.as_mut().unwrap().descendant_mut(&path);
+                        node.split_child(index, min_degree, right_id);
+                        self.report(&mut on_step, |tree| BTreeEvent::SplitChild {
+                            depth,
+                            child_index: index,
+                            median: &tree.node_at(&path).keys[index],
+                        });
+
+                        // Splitting moved one key up into the current node.
+                        // We now decide whether the new key belongs:
+                        // - to the left child
+                        // - exactly on the promoted key
+                        // - to the right child
+                        let node = self.root.as_mut().unwrap().descendant_mut(&path);
+                        if key > node.keys[index] {
+                            index += 1;
+                        } else if key == node.keys[index] {
+                            let old_value = std::mem::replace(&mut node.values[index], value);
+                            self.report(&mut on_step, |tree| BTreeEvent::ReplaceValue {
+                                depth,
+                                index,
+                                key: &tree.node_at(&path).keys[index],
+                            });
+                            return Some(old_value);
+                        }
+                    }
+
+                    self.report(&mut on_step, |_| BTreeEvent::Descend {
+                        depth,
+                        child_index: index,
+                    });
+                    path.push(index);
+                }
+            }
         }
+    }
+
+    /// Remove a key from the tree and return its value, if it was present.
+    pub fn remove(&mut self, key: &K) -> Option<V>
+    where
+        K: Ord,
+    {
+        let position = self.find(key)?;
+        Some(self.remove_at(position).1)
+    }
+
+    /// Keep only the entries for which `keep` returns `true`.
+    ///
+    /// Entries are removed one at a time in ascending order, so the tree is
+    /// rebalanced as it goes rather than rebuilt from scratch.
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        K: Ord,
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.extract_if(|key, value| !keep(key, value))
+            .for_each(drop);
+    }
+
+    /// Lazily remove and yield every entry for which `pred` returns `true`,
+    /// in ascending key order.
+    ///
+    /// Entries are only tested and removed as the iterator is advanced. If it
+    /// is dropped early, the remaining entries stay in the tree.
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, V, F>
+    where
+        K: Ord,
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        ExtractIf::new(self, pred)
+    }
+
+    /// Remove every entry. The minimum degree and observer are kept.
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    fn allocate_node_id(&mut self) -> usize {
+        let id = self.next_node_id;
+        self.next_node_id += 1;
+        id
+    }
+
+    /// The node reached by following `path` down from the root.
+    fn node_at(&self, path: &[usize]) -> &Node<K, V> {
+        self.root.as_ref().unwrap().descendant(path)
+    }
+
+    /// Tell the observer and `on_step` about the step that was just taken.
+    ///
+    /// `event` builds the event from the tree, so that the keys it mentions
+    /// can be borrowed from the tree itself.
+    fn report<F, E>(&mut self, on_step: &mut F, event: E)
+    where
+        F: FnMut(&Self, BTreeEvent<&K>),
+        E: Fn(&Self) -> BTreeEvent<&K>,
+    {
+        // Take the observer out for a moment so it can be called while the
+        // event borrows from the rest of the tree.
+        let mut observer = self.observer.take();
+        if let Some(observer) = observer.as_mut() {
+            observer.on_event(event(self));
+        }
+        // Put the observer back before `on_step` runs, so that a panic in
+        // `on_step` cannot leave the tree without it. That means building the
+        // event again, as the first one borrowed the tree while the observer
+        // was out.
+        self.observer = observer;
+
+        on_step(self, event(self));
+    }
+
+    /// Locate `key`. Nothing is reported, as the search may come up empty.
+    fn find(&self, key: &K) -> Option<Position>
+    where
+        K: Ord,
+    {
+        let mut node = self.root.as_ref()?;
+        let mut path = Vec::new();
 
-        result
+        loop {
+            match node.keys.binary_search(key) {
+                Ok(index) => return Some(Position { path, index }),
+                Err(_) if node.is_leaf() => return None,
+                Err(child_index) => {
+                    path.push(child_index);
+                    node = &node.children[child_index];
+                }
+            }
+        }
+    }
+
+    /// Position of the first entry in the tree.
+    fn first_position(&self) -> Option<Position> {
+        let root = self.root.as_ref()?;
+        Some(Position {
+            path: vec![0; root.height() - 1],
+            index: 0,
+        })
+    }
+
+    /// Position of the entry that follows `position` in key order.
+    fn next_position(&self, mut position: Position) -> Option<Position> {
+        let root = self.root.as_ref()?;
+        let node = root.descendant(&position.path);
+
+        // Internal node: the next entry is the smallest one in the subtree to
+        // the right of the current key.
+        if !node.is_leaf() {
+            let mut child = &node.children[position.index + 1];
+            position.path.push(position.index + 1);
+            while !child.is_leaf() {
+                child = &child.children[0];
+                position.path.push(0);
+            }
+            position.index = 0;
+            return Some(position);
+        }
+
+        if position.index + 1 < node.keys.len() {
+            position.index += 1;
+            return Some(position);
+        }
+
+        // End of a leaf: climb until we arrive from a child that still has a
+        // separator key to its right.
+        while let Some(child_index) = position.path.pop() {
+            if child_index < root.descendant(&position.path).keys.len() {
+                position.index = child_index;
+                return Some(position);
+            }
+        }
+
+        None
+    }
+
+    /// Position of the smallest entry whose key is greater than `key`.
+    fn position_after(&self, key: &K) -> Option<Position>
+    where
+        K: Ord,
+    {
+        let mut node = self.root.as_ref()?;
+        let mut path = Vec::new();
+        let mut best = None;
+
+        loop {
+            let index = node.keys.partition_point(|candidate| candidate <= key);
+            // Anything found deeper is smaller than this key, so it replaces
+            // the current best guess.
+            if index < node.keys.len() {
+                best = Some(Position {
+                    path: path.clone(),
+                    index,
+                });
+            }
+            if node.is_leaf() {
+                return best;
+            }
+            path.push(index);
+            node = &node.children[index];
+        }
+    }
+
+    /// Remove the entry at `position` and restore the B-tree rules.
+    ///
+    /// Entries are always taken out of a leaf. An entry in an internal node is
+    /// first swapped with its predecessor, the largest key of its left
+    /// subtree, which always lives in a leaf.
+    ///
+    /// The walk down to the entry, and on to its predecessor, is reported as
+    /// `Descend` events first, so the observer only hears about descents that
+    /// lead to an actual removal.
+    fn remove_at(&mut self, position: Position) -> (K, V) {
+        let Position { mut path, index } = position;
+        let depth = path.len();
+        for (depth, &child_index) in path.iter().enumerate() {
+            notify(
+                &mut self.observer,
+                BTreeEvent::Descend { depth, child_index },
+            );
+        }
+
+        let root = self.root.as_mut().unwrap();
+        let node = root.descendant_mut(&path);
+
+        let entry = if node.is_leaf() {
+            let key = node.keys.remove(index);
+            let value = node.values.remove(index);
+            notify(
+                &mut self.observer,
+                BTreeEvent::RemoveFromLeaf {
+                    depth,
+                    index,
+                    key: &key,
+                },
+            );
+            (key, value)
+        } else {
+            let mut child_index = index;
+            let mut leaf = &mut node.children[index];
+            loop {
+                notify(
+                    &mut self.observer,
+                    BTreeEvent::Descend {
+                        depth: path.len(),
+                        child_index,
+                    },
+                );
+                path.push(child_index);
+                if leaf.is_leaf() {
+                    break;
+                }
+                child_index = leaf.children.len() - 1;
+                leaf = leaf.children.last_mut().unwrap();
+            }
+            let predecessor_key = leaf.keys.pop().unwrap();
+            let predecessor_value = leaf.values.pop().unwrap();
+
+            let node = root.descendant_mut(&path[..depth]);
+            let key = std::mem::replace(&mut node.keys[index], predecessor_key);
+            let value = std::mem::replace(&mut node.values[index], predecessor_value);
+            notify(
+                &mut self.observer,
+                BTreeEvent::ReplaceWithPredecessor {
+                    depth,
+                    index,
+                    predecessor: &node.keys[index],
+                },
+            );
+            (key, value)
+        };
+
+        self.len -= 1;
+        self.rebalance(path);
+        entry
+    }
+
+    /// Fix the node at `path` after it lost a key, walking back up the tree
+    /// for as long as merges leave the parent short of keys too.
+    fn rebalance(&mut self, mut path: Vec<usize>) {
+        let min_keys = self.min_degree - 1;
+
+        while let Some(child_index) = path.pop() {
+            let depth = path.len();
+            let parent = self.root.as_mut().unwrap().descendant_mut(&path);
+            if parent.children[child_index].keys.len() >= min_keys {
+                return;
+            }
+
+            let left_has_spare =
+                child_index > 0 && parent.children[child_index - 1].keys.len() > min_keys;
+            let right_has_spare = parent
+                .children
+                .get(child_index + 1)
+                .is_some_and(|right| right.keys.len() > min_keys);
+
+            if left_has_spare {
+                parent.borrow_from_left(child_index);
+                notify(
+                    &mut self.observer,
+                    BTreeEvent::BorrowFromLeft {
+                        depth,
+                        child_index,
+                        separator: &parent.keys[child_index - 1],
+                    },
+                );
+                return;
+            }
+
+            if right_has_spare {
+                parent.borrow_from_right(child_index);
+                notify(
+                    &mut self.observer,
+                    BTreeEvent::BorrowFromRight {
+                        depth,
+                        child_index,
+                        separator: &parent.keys[child_index],
+                    },
+                );
+                return;
+            }
+
+            // Neither sibling can spare a key, so merge with one of them. This
+            // takes a key from the parent, which may now be short itself.
+            let left_index = child_index.saturating_sub(1);
+            let separator_index = parent.children[left_index].keys.len();
+            parent.merge_children(left_index);
+            notify(
+                &mut self.observer,
+                BTreeEvent::MergeChildren {
+                    depth,
+                    child_index: left_index,
+                    separator: &parent.children[left_index].keys[separator_index],
+                },
+            );
+        }
+
+        // The root is allowed to run low, but not empty. An empty internal
+        // root is replaced by its only child, and an empty leaf root means the
+        // tree is empty.
+        let root = self.root.as_mut().unwrap();
+        if root.keys.is_empty() {
+            self.root = root.children.pop();
+            if let Some(new_root) = &self.root {
+                notify(
+                    &mut self.observer,
+                    BTreeEvent::ShrinkRoot {
+                        height: new_root.height(),
+                    },
+                );
+            }
+        }
     }
 
     pub fn snapshot(&self) -> BTreeSnapshot
@@ -264,22 +929,162 @@ impl<K, V> BTree<K, V> {
         K: Display,
         V: Display,
     {
-        let mut next_id = 0;
+        self.snapshot_with(ToString::to_string, ToString::to_string)
+    }
+
+    /// Like [`BTree::snapshot`], but formats keys and values with `Debug`.
+    ///
+    /// Useful for keys such as structs or byte strings that have no `Display`.
+    pub fn snapshot_debug(&self) -> BTreeSnapshot
+    where
+        K: Debug,
+        V: Debug,
+    {
+        self.snapshot_with(|key| format!("{key:?}"), |value| format!("{value:?}"))
+    }
 
+    /// Build a snapshot using the given closures to turn keys and values into
+    /// the labels shown by the visualizer.
+    pub fn snapshot_with<FK, FV>(&self, mut key_fmt: FK, mut value_fmt: FV) -> BTreeSnapshot
+    where
+        FK: FnMut(&K) -> String,
+        FV: FnMut(&V) -> String,
+    {
         BTreeSnapshot {
             min_degree: self.min_degree,
             len: self.len,
             root: self
                 .root
                 .as_ref()
-                .map(|root| root.to_snapshot(0, &mut next_id)),
+                .map(|root| root.to_snapshot(0, &mut key_fmt, &mut value_fmt)),
+        }
+    }
+}
+
+impl<K: Debug, V: Debug> Debug for BTree<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Cloning copies the keys, values and shape of the tree. The observer is not
+/// cloned, so the new tree starts without one.
+impl<K: Clone, V: Clone> Clone for BTree<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            min_degree: self.min_degree,
+            root: self.root.clone(),
+            len: self.len,
+            next_node_id: self.next_node_id,
+            observer: None,
+        }
+    }
+}
+
+impl<K, V> Default for BTree<K, V> {
+    fn default() -> Self {
+        Self::new(DEFAULT_MIN_DEGREE)
+    }
+}
+
+/// Two trees are equal when they hold the same entries, even if their minimum
+/// degree or insert order gave them different shapes.
+impl<K: PartialEq, V: PartialEq> PartialEq for BTree<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: Eq, V: Eq> Eq for BTree<K, V> {}
+
+impl<K: PartialOrd, V: PartialOrd> PartialOrd for BTree<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<K: Ord, V: Ord> Ord for BTree<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<K: Hash, V: Hash> Hash for BTree<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len);
+        for entry in self.iter() {
+            entry.hash(state);
+        }
+    }
+}
+
+impl<K: Ord, V> Index<&K> for BTree<K, V> {
+    type Output = V;
+
+    /// Returns the value stored for `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not present in the tree.
+    fn index(&self, key: &K) -> &V {
+        self.get(key).expect("key not found in BTree")
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for BTree<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut tree = Self::default();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<K: Ord, V, const N: usize> From<[(K, V); N]> for BTree<K, V> {
+    fn from(entries: [(K, V); N]) -> Self {
+        Self::from_iter(entries)
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for BTree<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
         }
     }
 }
 
+impl<'a, K: Ord + Copy, V: Copy> Extend<(&'a K, &'a V)> for BTree<K, V> {
+    fn extend<I: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().map(|(key, value)| (*key, *value)));
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a BTree<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K, V> IntoIterator for BTree<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter::new(self.root, self.len)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::BTree;
+    use super::{
+        BTree, BTreeEvent, BTreeRecorder, DEFAULT_MIN_DEGREE, Node, RangeStep, SearchStep,
+    };
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::ops::Bound;
 
     #[test]
     fn empty_tree_has_no_values() {
@@ -368,9 +1173,579 @@ mod tests {
         assert_eq!(tree.get(&6), Some(&600));
     }
 
+    #[test]
+    fn snapshot_with_uses_the_given_formatters() {
+        let mut tree = BTree::new(2);
+        for key in [3, 1, 2] {
+            tree.insert(key, key * 100);
+        }
+
+        let snapshot = tree.snapshot_with(|key| format!("k{key}"), |value| format!("{value:#x}"));
+        let root = snapshot.root.expect("tree should not be empty");
+
+        assert_eq!(root.keys, vec!["k1", "k2", "k3"]);
+        assert_eq!(root.values, vec!["0x64", "0xc8", "0x12c"]);
+    }
+
+    #[test]
+    fn snapshot_debug_works_without_display() {
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        let mut tree = BTree::new(2);
+        tree.insert(Point { x: 1, y: 2 }, b"ab".to_vec());
+
+        let root = tree
+            .snapshot_debug()
+            .root
+            .expect("tree should not be empty");
+
+        assert_eq!(root.keys, vec!["Point { x: 1, y: 2 }"]);
+        assert_eq!(root.values, vec!["[97, 98]"]);
+    }
+
     #[test]
     #[should_panic(expected = "minimum degree")]
     fn minimum_degree_must_be_at_least_two() {
         let _tree: BTree<i32, i32> = BTree::new(1);
     }
+
+    #[test]
+    fn iter_visits_keys_in_order() {
+        let mut tree = BTree::new(2);
+        for key in [50, 40, 60, 30, 70, 20, 80, 10, 90, 0] {
+            tree.insert(key, key + 1);
+        }
+
+        let entries: Vec<_> = tree.iter().map(|(key, value)| (*key, *value)).collect();
+
+        assert_eq!(tree.iter().len(), 10);
+        assert_eq!(
+            entries,
+            (0..=90)
+                .step_by(10)
+                .map(|key| (key, key + 1))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            tree.into_iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            (0..=90).step_by(10).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn iter_runs_from_both_ends() {
+        let tree: BTree<i32, i32> = (0..100).map(|key| (key, key * 2)).collect();
+
+        assert_eq!(
+            tree.iter().rev().map(|(key, _)| *key).collect::<Vec<_>>(),
+            (0..100).rev().collect::<Vec<_>>()
+        );
+
+        let mut iter = tree.iter();
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        for step in 0.. {
+            let entry = if step % 3 == 0 {
+                iter.next_back().map(|entry| (entry, &mut back))
+            } else {
+                iter.next().map(|entry| (entry, &mut front))
+            };
+            let Some(((key, value), side)) = entry else {
+                break;
+            };
+            assert_eq!(*value, key * 2);
+            side.push(*key);
+            assert_eq!(iter.len(), 100 - front.len() - back.len());
+        }
+        back.reverse();
+        front.extend(back);
+
+        assert_eq!(front, (0..100).collect::<Vec<_>>());
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+    }
+
+    #[test]
+    fn equality_ignores_the_shape() {
+        let small: BTree<i32, i32> = (0..50).map(|key| (key, key)).collect();
+        let mut wide = BTree::new(4);
+        wide.extend((0..50).rev().map(|key| (key, key)));
+
+        assert_ne!(small.snapshot(), wide.snapshot());
+        assert_eq!(small, wide);
+        assert_eq!(hash_of(&small), hash_of(&wide));
+
+        wide.insert(7, 700);
+        assert_ne!(small, wide);
+        assert!(small < wide);
+    }
+
+    #[test]
+    fn clone_is_independent_of_the_original() {
+        let original = BTree::from([(1, "one".to_string()), (2, "two".to_string())]);
+        let mut copy = original.clone();
+
+        copy.insert(3, "three".to_string());
+
+        assert_eq!(original.len(), 2);
+        assert_eq!(copy.len(), 3);
+        assert_eq!(copy.snapshot().min_degree, original.min_degree());
+    }
+
+    #[test]
+    fn debug_prints_like_a_map() {
+        let tree = BTree::from([(2, "b"), (1, "a")]);
+
+        assert_eq!(format!("{tree:?}"), r#"{1: "a", 2: "b"}"#);
+    }
+
+    #[test]
+    fn default_and_index() {
+        let mut tree = BTree::default();
+        tree.extend([(&1, &10), (&2, &20)]);
+
+        assert_eq!(tree.min_degree(), DEFAULT_MIN_DEGREE);
+        assert_eq!(tree[&2], 20);
+    }
+
+    #[test]
+    #[should_panic(expected = "key not found")]
+    fn index_panics_for_missing_keys() {
+        let tree: BTree<i32, i32> = BTree::default();
+        let _ = tree[&1];
+    }
+
+    fn hash_of<T: Hash>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Check every B-tree rule and that `len` matches the stored keys.
+    fn assert_valid<K: Ord + std::fmt::Debug, V>(tree: &BTree<K, V>) {
+        fn check<K: Ord + std::fmt::Debug, V>(
+            node: &Node<K, V>,
+            min_degree: usize,
+            depth: usize,
+            leaf_depths: &mut Vec<usize>,
+        ) -> usize {
+            let min_keys = if depth == 0 { 1 } else { min_degree - 1 };
+            assert!(
+                (min_keys..2 * min_degree).contains(&node.keys.len()),
+                "node {:?} at depth {depth} has the wrong number of keys",
+                node.keys
+            );
+            assert_eq!(node.keys.len(), node.values.len());
+            assert!(node.keys.windows(2).all(|pair| pair[0] < pair[1]));
+
+            if node.is_leaf() {
+                leaf_depths.push(depth);
+                return node.keys.len();
+            }
+
+            assert_eq!(node.children.len(), node.keys.len() + 1);
+            node.keys.len()
+                + node
+                    .children
+                    .iter()
+                    .map(|child| check(child, min_degree, depth + 1, leaf_depths))
+                    .sum::<usize>()
+        }
+
+        let mut leaf_depths = Vec::new();
+        let count = tree
+            .root
+            .as_ref()
+            .map_or(0, |root| check(root, tree.min_degree, 0, &mut leaf_depths));
+
+        assert_eq!(count, tree.len());
+        assert!(leaf_depths.windows(2).all(|pair| pair[0] == pair[1]));
+        assert!(tree.keys().zip(tree.keys().skip(1)).all(|(a, b)| a < b));
+    }
+
+    #[test]
+    fn remove_returns_the_value_and_keeps_the_tree_valid() {
+        for min_degree in [2, 3, 4] {
+            let mut tree = BTree::new(min_degree);
+            // A fixed shuffle of 0..200 so that removals hit leaves, internal
+            // nodes, borrows and merges.
+            let keys: Vec<i32> = (0..200).map(|i| (i * 73) % 200).collect();
+            for &key in &keys {
+                tree.insert(key, key * 2);
+            }
+
+            for (removed, key) in keys.iter().rev().enumerate() {
+                assert_eq!(tree.remove(key), Some(key * 2));
+                assert_eq!(tree.get(key), None);
+                assert_eq!(tree.len(), keys.len() - removed - 1);
+                assert_valid(&tree);
+            }
+
+            assert!(tree.is_empty());
+            assert!(tree.root.is_none());
+        }
+    }
+
+    #[test]
+    fn removing_a_missing_key_changes_nothing() {
+        let mut tree: BTree<i32, i32> = (0..20).map(|key| (key * 2, key)).collect();
+        let before = tree.snapshot();
+
+        assert_eq!(tree.remove(&7), None);
+        assert_eq!(tree.snapshot(), before);
+        assert_eq!(BTree::<i32, i32>::new(2).remove(&7), None);
+    }
+
+    #[test]
+    fn retain_keeps_matching_entries_and_stays_balanced() {
+        let mut tree = BTree::new(2);
+        for key in 0..100 {
+            tree.insert(key, key);
+        }
+
+        tree.retain(|key, value| {
+            *value += 1;
+            key % 3 == 0
+        });
+
+        assert_valid(&tree);
+        assert_eq!(tree.len(), 34);
+        assert!(
+            tree.iter()
+                .all(|(key, value)| key % 3 == 0 && *value == key + 1)
+        );
+    }
+
+    #[test]
+    fn extract_if_yields_removed_entries_in_order() {
+        let mut tree: BTree<i32, String> = (0..50).map(|key| (key, key.to_string())).collect();
+
+        let removed: Vec<_> = tree.extract_if(|key, _| key % 10 == 0).collect();
+
+        assert_eq!(
+            removed,
+            [0, 10, 20, 30, 40].map(|key| (key, key.to_string()))
+        );
+        assert_eq!(tree.len(), 45);
+        assert_valid(&tree);
+    }
+
+    #[test]
+    fn extract_if_is_lazy() {
+        let mut tree = BTree::new(2);
+        for key in 0..30 {
+            tree.insert(key, ());
+        }
+
+        let mut tested = 0;
+        let first_two: Vec<_> = tree
+            .extract_if(|_, _| {
+                tested += 1;
+                true
+            })
+            .take(2)
+            .map(|(key, _)| key)
+            .collect();
+
+        assert_eq!(first_two, vec![0, 1]);
+        assert_eq!(tested, 2);
+        assert_eq!(tree.len(), 28);
+        assert_valid(&tree);
+    }
+
+    #[test]
+    fn clear_empties_the_tree() {
+        let mut tree: BTree<i32, i32> = (0..10).map(|key| (key, key)).collect();
+
+        tree.clear();
+
+        assert!(tree.is_empty());
+        assert_eq!(tree.iter().next(), None);
+        tree.insert(1, 1);
+        assert_eq!(tree.len(), 1);
+    }
+
+    #[test]
+    fn node_ids_are_stable_across_splits() {
+        let mut tree = BTree::new(2);
+        for key in [10, 20, 5] {
+            tree.insert(key, key);
+        }
+        let leaf_id = tree.snapshot().root.unwrap().id;
+
+        // The full root is split: the old root keeps its id as the left half
+        // and both the new root and the right half get fresh ids.
+        tree.insert(6, 6);
+        let root = tree.snapshot().root.unwrap();
+
+        assert_eq!(root.children[0].id, leaf_id);
+        assert_ne!(root.id, leaf_id);
+        assert_ne!(root.children[1].id, leaf_id);
+        assert_ne!(root.children[1].id, root.id);
+
+        tree.insert(7, 7);
+        let after = tree.snapshot().root.unwrap();
+        assert_eq!(after.id, root.id);
+        assert_eq!(after.children[0].id, leaf_id);
+    }
+
+    #[test]
+    fn insert_traced_sees_the_tree_after_every_step() {
+        let mut tree = BTree::new(2);
+        for key in [10, 20, 5] {
+            tree.insert(key, key);
+        }
+
+        let mut steps = Vec::new();
+        tree.insert_traced(6, 6, |tree, event| {
+            let root = tree.snapshot().root.unwrap();
+            steps.push((event.cloned(), root.keys, tree.len()));
+        });
+
+        assert_eq!(
+            steps,
+            vec![
+                (BTreeEvent::GrowRoot { height: 2 }, vec![], 3),
+                (
+                    BTreeEvent::SplitChild {
+                        depth: 0,
+                        child_index: 0,
+                        median: 10
+                    },
+                    vec!["10".to_string()],
+                    3
+                ),
+                (
+                    BTreeEvent::Descend {
+                        depth: 0,
+                        child_index: 0
+                    },
+                    vec!["10".to_string()],
+                    3
+                ),
+                (
+                    BTreeEvent::InsertIntoLeaf {
+                        depth: 1,
+                        index: 1,
+                        key: 6
+                    },
+                    vec!["10".to_string()],
+                    4
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn insert_and_insert_traced_take_the_same_steps() {
+        let keys: Vec<i32> = (0..200).map(|i| (i * 37) % 101).collect();
+        let plain_recorder = BTreeRecorder::new();
+        let traced_recorder = BTreeRecorder::new();
+        let mut plain = BTree::new(2);
+        let mut traced = BTree::new(2);
+        plain.set_observer(plain_recorder.clone());
+        traced.set_observer(traced_recorder.clone());
+
+        let mut traced_steps = Vec::new();
+        for &key in &keys {
+            assert_eq!(
+                plain.insert(key, key),
+                traced.insert_traced(key, key, |_, event| traced_steps.push(event.cloned()))
+            );
+        }
+
+        let events = plain_recorder.take_events();
+        assert_eq!(events, traced_recorder.take_events());
+        assert_eq!(events, traced_steps);
+        assert_eq!(plain.snapshot(), traced.snapshot());
+    }
+
+    #[test]
+    fn panicking_on_step_keeps_the_observer() {
+        let recorder = BTreeRecorder::new();
+        let mut tree = BTree::new(2);
+        tree.set_observer(recorder.clone());
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            tree.insert_traced(1, 1, |_, _| panic!("on_step failed"));
+        }));
+        assert!(result.is_err());
+        assert_eq!(recorder.take_events().len(), 1);
+
+        tree.insert(2, 2);
+        assert!(tree.take_observer().is_some());
+        assert_eq!(
+            recorder.take_events(),
+            vec![BTreeEvent::InsertIntoLeaf {
+                depth: 0,
+                index: 1,
+                key: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn search_path_follows_the_lookup() {
+        let mut tree = BTree::new(2);
+        for key in [10, 20, 5, 6, 30, 40, 35] {
+            tree.insert(key, key);
+        }
+        // Root [10, 30] with children [5, 6], [20] and [35, 40].
+        let root = tree.snapshot().root.unwrap();
+        assert_eq!(root.keys, vec!["10", "30"]);
+
+        assert_eq!(
+            tree.search_path(&30),
+            vec![SearchStep {
+                node_id: root.id,
+                depth: 0,
+                comparison: Ok(1)
+            }]
+        );
+        assert_eq!(
+            tree.search_path(&40),
+            vec![
+                SearchStep {
+                    node_id: root.id,
+                    depth: 0,
+                    comparison: Err(2)
+                },
+                SearchStep {
+                    node_id: root.children[2].id,
+                    depth: 1,
+                    comparison: Ok(1)
+                },
+            ]
+        );
+
+        let miss = tree.search_path(&25);
+        assert_eq!(miss.len(), 2);
+        assert_eq!(miss[1].node_id, root.children[1].id);
+        assert_eq!(miss[1].comparison, Err(1));
+        assert!(BTree::<i32, i32>::new(2).search_path(&1).is_empty());
+    }
+
+    #[test]
+    fn range_path_walks_only_the_range() {
+        let mut tree = BTree::new(2);
+        for key in [10, 20, 5, 6, 30, 40, 35] {
+            tree.insert(key, key);
+        }
+        // Root [10, 30] with children [5, 6], [20] and [35, 40].
+        let root = tree.snapshot().root.unwrap();
+        let [left, middle, right] = [0, 1, 2].map(|index| root.children[index].id);
+
+        assert_eq!(
+            tree.range_path(6..=30),
+            vec![
+                RangeStep::Enter {
+                    node_id: root.id,
+                    depth: 0
+                },
+                RangeStep::Enter {
+                    node_id: left,
+                    depth: 1
+                },
+                RangeStep::Yield {
+                    node_id: left,
+                    index: 1
+                },
+                RangeStep::Yield {
+                    node_id: root.id,
+                    index: 0
+                },
+                RangeStep::Enter {
+                    node_id: middle,
+                    depth: 1
+                },
+                RangeStep::Yield {
+                    node_id: middle,
+                    index: 0
+                },
+                RangeStep::Yield {
+                    node_id: root.id,
+                    index: 1
+                },
+                RangeStep::Enter {
+                    node_id: right,
+                    depth: 1
+                },
+                RangeStep::Stop {
+                    node_id: right,
+                    index: 0
+                },
+            ]
+        );
+
+        // A range above every key in the left child never enters it, and an
+        // exclusive end stops at the key equal to it.
+        let steps = tree.range_path(20..30);
+        assert!(!steps.contains(&RangeStep::Enter {
+            node_id: left,
+            depth: 1
+        }));
+        assert_eq!(
+            steps.last(),
+            Some(&RangeStep::Stop {
+                node_id: root.id,
+                index: 1
+            })
+        );
+
+        // Starting at a key of the root skips the child to its left.
+        assert_eq!(
+            tree.range_path(10..=10)[..2],
+            [
+                RangeStep::Enter {
+                    node_id: root.id,
+                    depth: 0
+                },
+                RangeStep::Yield {
+                    node_id: root.id,
+                    index: 0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn range_path_yields_the_keys_in_order() {
+        let tree: BTree<i32, i32> = (0..200).map(|key| (key * 3 % 200, key)).collect();
+        let snapshot = tree.snapshot();
+        let yielded = |steps: Vec<RangeStep>| -> Vec<i32> {
+            steps
+                .into_iter()
+                .filter_map(|step| match step {
+                    RangeStep::Yield { node_id, index } => Some((node_id, index)),
+                    _ => None,
+                })
+                .map(|(node_id, index)| {
+                    let mut stack = vec![snapshot.root.as_ref().unwrap()];
+                    while let Some(node) = stack.pop() {
+                        if node.id == node_id {
+                            return node.keys[index].parse().unwrap();
+                        }
+                        stack.extend(&node.children);
+                    }
+                    panic!("node {node_id} is not in the tree");
+                })
+                .collect()
+        };
+
+        assert_eq!(yielded(tree.range_path(..)), (0..200).collect::<Vec<_>>());
+        assert_eq!(
+            yielded(tree.range_path(50..=60)),
+            (50..=60).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            yielded(tree.range_path((Bound::Excluded(197), Bound::Unbounded))),
+            vec![198, 199]
+        );
+        assert!(yielded(tree.range_path(300..)).is_empty());
+        assert!(BTree::<i32, i32>::new(2).range_path(..).is_empty());
+    }
 }
@@ -0,0 +1,654 @@
+//! Structured events that explain how a `BTree` reached its current shape.
+//!
+//! Observers are opt-in: a tree has none until `BTree::set_observer` is
+//! called. While one is installed, the tree hands it a `BTreeEvent<&K>` for
+//! each step it takes, borrowing the keys involved. `BTreeRecorder` clones those
+//! events into an owned `Vec<BTreeEvent<K>>` that tests and tools can inspect
+//! afterwards.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// One step taken by the tree while it was being modified.
+///
+/// `depth` always refers to the node where the step happened, with the root
+/// at depth 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BTreeEvent<K> {
+    /// The search left the node at `depth` for its child `child_index`.
+    Descend { depth: usize, child_index: usize },
+    /// The root was full, so a new empty root was placed above it. `height`
+    /// is the number of levels once the old root has been split.
+    GrowRoot { height: usize },
+    /// Child `child_index` of the node at `depth` was full and got split in
+    /// two. `median` moved up into the node at `depth`.
+    SplitChild {
+        depth: usize,
+        child_index: usize,
+        median: K,
+    },
+    /// `key` was already stored at position `index`, so only its value was
+    /// replaced.
+    ReplaceValue { depth: usize, index: usize, key: K },
+    /// `key` was added to a leaf at position `index`.
+    InsertIntoLeaf { depth: usize, index: usize, key: K },
+    /// `key` was taken out of a leaf at position `index`.
+    RemoveFromLeaf { depth: usize, index: usize, key: K },
+    /// The key being removed sat in an internal node at position `index`, so
+    /// it was overwritten by `predecessor`, the largest key of its left
+    /// subtree. The predecessor is then removed from its leaf.
+    ReplaceWithPredecessor {
+        depth: usize,
+        index: usize,
+        predecessor: K,
+    },
+    /// Child `child_index` of the node at `depth` ran short of keys and took
+    /// one from its left sibling. `separator` is the sibling key that moved up
+    /// into the node at `depth`.
+    BorrowFromLeft {
+        depth: usize,
+        child_index: usize,
+        separator: K,
+    },
+    /// Like `BorrowFromLeft`, but the key came from the right sibling.
+    BorrowFromRight {
+        depth: usize,
+        child_index: usize,
+        separator: K,
+    },
+    /// Children `child_index` and `child_index + 1` of the node at `depth`
+    /// were merged into one, pulling `separator` down between them.
+    MergeChildren {
+        depth: usize,
+        child_index: usize,
+        separator: K,
+    },
+    /// The root lost its last key, so its only child became the new root.
+    /// `height` is the number of levels left.
+    ShrinkRoot { height: usize },
+}
+
+impl<K> BTreeEvent<&K> {
+    /// Turn an event that borrows its keys into one that owns them.
+    pub fn cloned(&self) -> BTreeEvent<K>
+    where
+        K: Clone,
+    {
+        match *self {
+            BTreeEvent::Descend { depth, child_index } => {
+                BTreeEvent::Descend { depth, child_index }
+            }
+            BTreeEvent::GrowRoot { height } => BTreeEvent::GrowRoot { height },
+            BTreeEvent::SplitChild {
+                depth,
+                child_index,
+                median,
+            } => BTreeEvent::SplitChild {
+                depth,
+                child_index,
+                median: median.clone(),
+            },
+            BTreeEvent::ReplaceValue { depth, index, key } => BTreeEvent::ReplaceValue {
+                depth,
+                index,
+                key: key.clone(),
+            },
+            BTreeEvent::InsertIntoLeaf { depth, index, key } => BTreeEvent::InsertIntoLeaf {
+                depth,
+                index,
+                key: key.clone(),
+            },
+            BTreeEvent::RemoveFromLeaf { depth, index, key } => BTreeEvent::RemoveFromLeaf {
+                depth,
+                index,
+                key: key.clone(),
+            },
+            BTreeEvent::ReplaceWithPredecessor {
+                depth,
+                index,
+                predecessor,
+            } => BTreeEvent::ReplaceWithPredecessor {
+                depth,
+                index,
+                predecessor: predecessor.clone(),
+            },
+            BTreeEvent::BorrowFromLeft {
+                depth,
+                child_index,
+                separator,
+            } => BTreeEvent::BorrowFromLeft {
+                depth,
+                child_index,
+                separator: separator.clone(),
+            },
+            BTreeEvent::BorrowFromRight {
+                depth,
+                child_index,
+                separator,
+            } => BTreeEvent::BorrowFromRight {
+                depth,
+                child_index,
+                separator: separator.clone(),
+            },
+            BTreeEvent::MergeChildren {
+                depth,
+                child_index,
+                separator,
+            } => BTreeEvent::MergeChildren {
+                depth,
+                child_index,
+                separator: separator.clone(),
+            },
+            BTreeEvent::ShrinkRoot { height } => BTreeEvent::ShrinkRoot { height },
+        }
+    }
+}
+
+/// Receives every event emitted by a tree it is attached to.
+///
+/// Any `FnMut(BTreeEvent<&K>)` closure can be used as an observer.
+pub trait BTreeObserver<K> {
+    fn on_event(&mut self, event: BTreeEvent<&K>);
+}
+
+impl<K, F> BTreeObserver<K> for F
+where
+    F: FnMut(BTreeEvent<&K>),
+{
+    fn on_event(&mut self, event: BTreeEvent<&K>) {
+        self(event)
+    }
+}
+
+/// An observer that keeps a copy of every event it sees.
+///
+/// The recorder is a cheap handle: give one clone to the tree and keep
+/// another to read the events back. Events pile up until they are taken or
+/// cleared, so a recorder left on a long-lived tree should either be drained
+/// regularly or be created with `with_capacity`, which keeps only the most
+/// recent events.
+///
+/// ```
+/// use data_structures::btree::{BTree, BTreeEvent, BTreeRecorder};
+///
+/// let recorder = BTreeRecorder::new();
+/// let mut tree = BTree::new(2);
+/// tree.set_observer(recorder.clone());
+///
+/// tree.insert(1, "one");
+///
+/// assert_eq!(
+///     recorder.take_events(),
+///     vec![BTreeEvent::InsertIntoLeaf { depth: 0, index: 0, key: 1 }]
+/// );
+/// ```
+pub struct BTreeRecorder<K> {
+    events: Arc<Mutex<VecDeque<BTreeEvent<K>>>>,
+    capacity: Option<usize>,
+}
+
+impl<K> BTreeRecorder<K> {
+    pub fn new() -> Self {
+        Self {
+            events: Arc::new(Mutex::new(VecDeque::new())),
+            capacity: None,
+        }
+    }
+
+    /// A recorder that holds at most `capacity` events. Once it is full, each
+    /// new event pushes out the oldest one.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            events: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity: Some(capacity),
+        }
+    }
+
+    /// A copy of the events recorded so far.
+    pub fn events(&self) -> Vec<BTreeEvent<K>>
+    where
+        K: Clone,
+    {
+        self.events.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Remove and return the events recorded so far.
+    pub fn take_events(&self) -> Vec<BTreeEvent<K>> {
+        std::mem::take(&mut *self.events.lock().unwrap()).into()
+    }
+
+    /// Forget the events recorded so far.
+    pub fn clear(&self) {
+        self.events.lock().unwrap().clear();
+    }
+}
+
+impl<K> Clone for BTreeRecorder<K> {
+    fn clone(&self) -> Self {
+        Self {
+            events: Arc::clone(&self.events),
+            capacity: self.capacity,
+        }
+    }
+}
+
+impl<K> Default for BTreeRecorder<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Clone> BTreeObserver<K> for BTreeRecorder<K> {
+    fn on_event(&mut self, event: BTreeEvent<&K>) {
+        if self.capacity == Some(0) {
+            return;
+        }
+        let mut events = self.events.lock().unwrap();
+        if Some(events.len()) == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event.cloned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::BTreeEvent::{self, *};
+    use super::BTreeRecorder;
+    use crate::btree::BTree;
+
+    fn recorded_tree() -> (BTree<i32, i32>, BTreeRecorder<i32>) {
+        let recorder = BTreeRecorder::new();
+        let mut tree = BTree::new(2);
+        tree.set_observer(recorder.clone());
+        (tree, recorder)
+    }
+
+    #[test]
+    fn inserts_into_a_single_leaf() {
+        let (mut tree, recorder) = recorded_tree();
+
+        for key in [10, 20, 5] {
+            tree.insert(key, key);
+        }
+
+        assert_eq!(
+            recorder.take_events(),
+            vec![
+                InsertIntoLeaf {
+                    depth: 0,
+                    index: 0,
+                    key: 10
+                },
+                InsertIntoLeaf {
+                    depth: 0,
+                    index: 1,
+                    key: 20
+                },
+                InsertIntoLeaf {
+                    depth: 0,
+                    index: 0,
+                    key: 5
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn full_root_grows_the_tree() {
+        let (mut tree, recorder) = recorded_tree();
+        for key in [10, 20, 5] {
+            tree.insert(key, key);
+        }
+        recorder.take_events();
+
+        tree.insert(6, 6);
+
+        assert_eq!(
+            recorder.take_events(),
+            vec![
+                GrowRoot { height: 2 },
+                SplitChild {
+                    depth: 0,
+                    child_index: 0,
+                    median: 10
+                },
+                Descend {
+                    depth: 0,
+                    child_index: 0
+                },
+                InsertIntoLeaf {
+                    depth: 1,
+                    index: 1,
+                    key: 6
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn full_child_is_split_on_the_way_down() {
+        let (mut tree, recorder) = recorded_tree();
+        // Root [10] with children [5, 6] and [20, 30, 40].
+        for key in [10, 20, 5, 6, 30, 40] {
+            tree.insert(key, key);
+        }
+        recorder.take_events();
+
+        tree.insert(35, 35);
+
+        assert_eq!(
+            recorder.take_events(),
+            vec![
+                SplitChild {
+                    depth: 0,
+                    child_index: 1,
+                    median: 30
+                },
+                Descend {
+                    depth: 0,
+                    child_index: 2
+                },
+                InsertIntoLeaf {
+                    depth: 1,
+                    index: 0,
+                    key: 35
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn existing_keys_are_replaced_in_place() {
+        let (mut tree, recorder) = recorded_tree();
+        for key in [10, 20, 5, 6] {
+            tree.insert(key, key);
+        }
+        recorder.take_events();
+
+        tree.insert(10, 100);
+        tree.insert(6, 60);
+
+        assert_eq!(
+            recorder.take_events(),
+            vec![
+                ReplaceValue {
+                    depth: 0,
+                    index: 0,
+                    key: 10
+                },
+                Descend {
+                    depth: 0,
+                    child_index: 0
+                },
+                ReplaceValue {
+                    depth: 1,
+                    index: 1,
+                    key: 6
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn promoted_median_can_be_replaced() {
+        let (mut tree, recorder) = recorded_tree();
+        // Root [10] with children [5, 6, 7] and [20].
+        for key in [10, 20, 5, 6, 7] {
+            tree.insert(key, key);
+        }
+        recorder.take_events();
+
+        tree.insert(6, 600);
+
+        assert_eq!(
+            recorder.take_events(),
+            vec![
+                SplitChild {
+                    depth: 0,
+                    child_index: 0,
+                    median: 6
+                },
+                ReplaceValue {
+                    depth: 0,
+                    index: 0,
+                    key: 6
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn internal_key_is_replaced_by_its_predecessor() {
+        let (mut tree, recorder) = recorded_tree();
+        // Root [10] with children [5, 6] and [20].
+        for key in [10, 20, 5, 6] {
+            tree.insert(key, key);
+        }
+        recorder.take_events();
+
+        tree.remove(&10);
+
+        assert_eq!(
+            recorder.take_events(),
+            vec![
+                Descend {
+                    depth: 0,
+                    child_index: 0
+                },
+                ReplaceWithPredecessor {
+                    depth: 0,
+                    index: 0,
+                    predecessor: 6
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn walk_to_the_predecessor_is_reported_before_the_swap() {
+        let (mut tree, recorder) = recorded_tree();
+        // Root [4] over [2] and [6, 8], with the leaves [1] [3] [5] [7] [9, 10].
+        for key in 1..=10 {
+            tree.insert(key, key);
+        }
+        recorder.take_events();
+
+        tree.remove(&4);
+
+        assert_eq!(
+            recorder.take_events(),
+            vec![
+                Descend {
+                    depth: 0,
+                    child_index: 0
+                },
+                Descend {
+                    depth: 1,
+                    child_index: 1
+                },
+                ReplaceWithPredecessor {
+                    depth: 0,
+                    index: 0,
+                    predecessor: 3
+                },
+                MergeChildren {
+                    depth: 1,
+                    child_index: 0,
+                    separator: 2
+                },
+                BorrowFromRight {
+                    depth: 0,
+                    child_index: 0,
+                    separator: 6
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn removing_a_missing_key_reports_nothing() {
+        let (mut tree, recorder) = recorded_tree();
+        for key in 1..=10 {
+            tree.insert(key, key);
+        }
+        recorder.take_events();
+
+        assert_eq!(tree.remove(&42), None);
+        assert_eq!(tree.remove(&0), None);
+
+        assert!(recorder.take_events().is_empty());
+    }
+
+    #[test]
+    fn short_child_borrows_from_its_left_sibling() {
+        let (mut tree, recorder) = recorded_tree();
+        // Root [10] with children [5, 6] and [20].
+        for key in [10, 20, 5, 6] {
+            tree.insert(key, key);
+        }
+        recorder.take_events();
+
+        tree.remove(&20);
+
+        assert_eq!(
+            recorder.take_events(),
+            vec![
+                Descend {
+                    depth: 0,
+                    child_index: 1
+                },
+                RemoveFromLeaf {
+                    depth: 1,
+                    index: 0,
+                    key: 20
+                },
+                BorrowFromLeft {
+                    depth: 0,
+                    child_index: 1,
+                    separator: 6
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn short_child_borrows_from_its_right_sibling() {
+        let (mut tree, recorder) = recorded_tree();
+        // Root [10] with children [5] and [20, 30].
+        for key in [10, 20, 5, 30] {
+            tree.insert(key, key);
+        }
+        recorder.take_events();
+
+        tree.remove(&5);
+
+        assert_eq!(
+            recorder.take_events(),
+            vec![
+                Descend {
+                    depth: 0,
+                    child_index: 0
+                },
+                RemoveFromLeaf {
+                    depth: 1,
+                    index: 0,
+                    key: 5
+                },
+                BorrowFromRight {
+                    depth: 0,
+                    child_index: 0,
+                    separator: 20
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_can_shrink_the_root() {
+        let (mut tree, recorder) = recorded_tree();
+        // Root [10] with children [5] and [20].
+        for key in [10, 20, 5, 30] {
+            tree.insert(key, key);
+        }
+        tree.remove(&30);
+        recorder.take_events();
+
+        tree.remove(&5);
+
+        assert_eq!(
+            recorder.take_events(),
+            vec![
+                Descend {
+                    depth: 0,
+                    child_index: 0
+                },
+                RemoveFromLeaf {
+                    depth: 1,
+                    index: 0,
+                    key: 5
+                },
+                MergeChildren {
+                    depth: 0,
+                    child_index: 0,
+                    separator: 10
+                },
+                ShrinkRoot { height: 1 },
+            ]
+        );
+        assert_eq!(tree.snapshot().root.unwrap().keys, vec!["10", "20"]);
+    }
+
+    #[test]
+    fn closures_can_observe_the_tree() {
+        let seen = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&seen);
+        let mut tree = BTree::new(2);
+        tree.set_observer(move |_event: BTreeEvent<&i32>| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+        tree.insert(1, "one");
+        tree.insert(1, "uno");
+        assert_eq!(seen.load(Ordering::Relaxed), 2);
+
+        assert!(tree.take_observer().is_some());
+        tree.insert(2, "two");
+        assert_eq!(seen.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn bounded_recorder_keeps_the_latest_events() {
+        let recorder = BTreeRecorder::with_capacity(2);
+        let mut tree = BTree::new(2);
+        tree.set_observer(recorder.clone());
+
+        for key in [10, 20, 5] {
+            tree.insert(key, key);
+        }
+
+        assert_eq!(
+            recorder.events(),
+            vec![
+                InsertIntoLeaf {
+                    depth: 0,
+                    index: 1,
+                    key: 20
+                },
+                InsertIntoLeaf {
+                    depth: 0,
+                    index: 0,
+                    key: 5
+                },
+            ]
+        );
+
+        recorder.clear();
+        assert!(recorder.take_events().is_empty());
+        tree.insert(6, 6);
+        assert_eq!(recorder.events().len(), 2);
+    }
+}
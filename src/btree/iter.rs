@@ -0,0 +1,227 @@
+use std::iter::FusedIterator;
+
+use super::{BTree, Node, Position};
+
+/// Borrowing in-order iterator over the entries of a `BTree`.
+///
+/// The front stack holds the path from the root to the next entry. Each
+/// element is a node together with the index of the next key to yield from
+/// it. The back stack mirrors it for `next_back`, with each index counting
+/// the keys of its node that are still left to yield from the back. The two
+/// ends meet when `remaining` reaches zero.
+pub struct Iter<'a, K, V> {
+    stack: Vec<(&'a Node<K, V>, usize)>,
+    back_stack: Vec<(&'a Node<K, V>, usize)>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    pub(super) fn new(root: Option<&'a Node<K, V>>, len: usize) -> Self {
+        let mut iter = Self {
+            stack: Vec::new(),
+            back_stack: Vec::new(),
+            remaining: len,
+        };
+        if let Some(root) = root {
+            iter.push_leftmost(root);
+            iter.push_rightmost(root);
+        }
+        iter
+    }
+
+    /// Walk down the leftmost edge of `node`, so the smallest key of the
+    /// subtree ends up on top of the stack.
+    fn push_leftmost(&mut self, mut node: &'a Node<K, V>) {
+        loop {
+            self.stack.push((node, 0));
+            match node.children.first() {
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+    }
+
+    /// Walk down the rightmost edge of `node`, so the largest key of the
+    /// subtree ends up on top of the back stack.
+    fn push_rightmost(&mut self, mut node: &'a Node<K, V>) {
+        loop {
+            self.back_stack.push((node, node.keys.len()));
+            match node.children.last() {
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let (node, index) = self.stack.pop()?;
+
+        // Come back for the next key of this node once the subtree between
+        // the two keys has been visited.
+        if index + 1 < node.keys.len() {
+            self.stack.push((node, index + 1));
+        }
+        if let Some(child) = node.children.get(index + 1) {
+            self.push_leftmost(child);
+        }
+
+        self.remaining -= 1;
+        Some((&node.keys[index], &node.values[index]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let (node, left) = self.back_stack.pop()?;
+        let index = left - 1;
+
+        if index > 0 {
+            self.back_stack.push((node, index));
+        }
+        if let Some(child) = node.children.get(index) {
+            self.push_rightmost(child);
+        }
+
+        self.remaining -= 1;
+        Some((&node.keys[index], &node.values[index]))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
+
+impl<K, V> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            stack: self.stack.clone(),
+            back_stack: self.back_stack.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+/// Owning in-order iterator over the entries of a `BTree`.
+pub struct IntoIter<K, V> {
+    entries: std::vec::IntoIter<(K, V)>,
+}
+
+impl<K, V> IntoIter<K, V> {
+    pub(super) fn new(root: Option<Node<K, V>>, len: usize) -> Self {
+        let mut entries = Vec::with_capacity(len);
+        if let Some(root) = root {
+            flatten(root, &mut entries);
+        }
+        Self {
+            entries: entries.into_iter(),
+        }
+    }
+}
+
+/// Move every entry of `node` into `entries`, smallest key first.
+fn flatten<K, V>(node: Node<K, V>, entries: &mut Vec<(K, V)>) {
+    let mut children = node.children.into_iter();
+    for entry in node.keys.into_iter().zip(node.values) {
+        if let Some(child) = children.next() {
+            flatten(child, entries);
+        }
+        entries.push(entry);
+    }
+    if let Some(last_child) = children.next() {
+        flatten(last_child, entries);
+    }
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.entries.next_back()
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K, V> FusedIterator for IntoIter<K, V> {}
+
+/// Iterator returned by `BTree::extract_if`.
+///
+/// It remembers the position of the next entry to test. Kept entries are
+/// stepped over without touching the tree. After a removal the tree may have
+/// been rebalanced, so the position is found again by searching for the
+/// first key after the one just removed.
+pub struct ExtractIf<'a, K, V, F> {
+    tree: &'a mut BTree<K, V>,
+    pred: F,
+    next: Option<Position>,
+}
+
+impl<'a, K, V, F> ExtractIf<'a, K, V, F> {
+    pub(super) fn new(tree: &'a mut BTree<K, V>, pred: F) -> Self {
+        let next = tree.first_position();
+        Self { tree, pred, next }
+    }
+}
+
+impl<K, V, F> Iterator for ExtractIf<'_, K, V, F>
+where
+    K: Ord,
+    F: FnMut(&K, &mut V) -> bool,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let position = self.next.take()?;
+            let node = self
+                .tree
+                .root
+                .as_mut()
+                .unwrap()
+                .descendant_mut(&position.path);
+
+            if (self.pred)(&node.keys[position.index], &mut node.values[position.index]) {
+                let (key, value) = self.tree.remove_at(position);
+                self.next = self.tree.position_after(&key);
+                return Some((key, value));
+            }
+
+            self.next = self.tree.next_position(position);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.tree.len))
+    }
+}
+
+impl<K, V, F> FusedIterator for ExtractIf<'_, K, V, F>
+where
+    K: Ord,
+    F: FnMut(&K, &mut V) -> bool,
+{
+}
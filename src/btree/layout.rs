@@ -0,0 +1,525 @@
+//! Positions for drawing a `BTreeSnapshot`.
+//!
+//! The layout only deals in plain `f32` boxes, so any frontend can use it:
+//! the egui visualizer, the SVG export, or something else entirely. Every
+//! subtree gets its own horizontal band and sibling bands never overlap, so
+//! nodes never overlap either. Each parent sits centered over its first and
+//! last child.
+//!
+//! Nodes can be collapsed by id. A collapsed node is laid out like a leaf and
+//! carries a `SubtreeSummary` of what it hides instead of its children.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use super::{BTreeNodeSnapshot, BTreeSnapshot};
+
+/// Sizes used to lay out a tree. The default matches the visualizer's
+/// 14-point monospace font.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayoutConfig {
+    pub node_height: f32,
+    /// Space between a node's border and its label, on each side.
+    pub node_padding: f32,
+    /// Width of one character of a label.
+    pub char_width: f32,
+    /// Smallest space between two neighbouring subtrees.
+    pub horizontal_gap: f32,
+    /// Space between the bottom of a node and the top of its children.
+    pub vertical_gap: f32,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            node_height: 34.0,
+            node_padding: 14.0,
+            char_width: 8.0,
+            horizontal_gap: 30.0,
+            vertical_gap: 88.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// An axis-aligned box, given by its top-left corner and size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    pub fn center(&self) -> Point {
+        Point {
+            x: self.x + self.width / 2.0,
+            y: self.y + self.height / 2.0,
+        }
+    }
+
+    pub fn center_top(&self) -> Point {
+        Point {
+            x: self.x + self.width / 2.0,
+            y: self.y,
+        }
+    }
+
+    pub fn center_bottom(&self) -> Point {
+        Point {
+            x: self.x + self.width / 2.0,
+            y: self.bottom(),
+        }
+    }
+
+    /// Whether the two boxes share any area. Touching edges do not count.
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+}
+
+/// Where one node of the snapshot goes, and what it shows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayoutNode {
+    /// Stable id of the node, matching `BTreeNodeSnapshot::id`.
+    pub id: usize,
+    pub rect: Rect,
+    /// The `key:value` entries of the node, in key order.
+    pub entries: Vec<String>,
+    /// The entries joined by ` | `, as drawn inside the box.
+    pub label: String,
+    pub depth: usize,
+    pub is_leaf: bool,
+    pub key_count: usize,
+    pub child_count: usize,
+    /// Set when the node is collapsed. Its box is then twice as tall, with
+    /// the label in the top half and the summary in the bottom half.
+    pub collapsed: Option<SubtreeSummary>,
+}
+
+/// What a collapsed node hides: every node and key below it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubtreeSummary {
+    pub node_count: usize,
+    pub key_count: usize,
+    /// Smallest and largest hidden key.
+    pub first_key: String,
+    pub last_key: String,
+}
+
+impl SubtreeSummary {
+    fn new(node: &BTreeNodeSnapshot) -> Self {
+        let mut summary = Self {
+            node_count: 0,
+            key_count: 0,
+            first_key: leftmost_key(&node.children[0]),
+            last_key: rightmost_key(&node.children[node.children.len() - 1]),
+        };
+        let mut stack: Vec<&BTreeNodeSnapshot> = node.children.iter().collect();
+        while let Some(node) = stack.pop() {
+            summary.node_count += 1;
+            summary.key_count += node.keys.len();
+            stack.extend(&node.children);
+        }
+        summary
+    }
+}
+
+impl fmt::Display for SubtreeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "+{} keys, {}..{}",
+            self.key_count, self.first_key, self.last_key
+        )
+    }
+}
+
+fn leftmost_key(mut node: &BTreeNodeSnapshot) -> String {
+    while let Some(child) = node.children.first() {
+        node = child;
+    }
+    node.keys.first().cloned().unwrap_or_default()
+}
+
+fn rightmost_key(mut node: &BTreeNodeSnapshot) -> String {
+    while let Some(child) = node.children.last() {
+        node = child;
+    }
+    node.keys.last().cloned().unwrap_or_default()
+}
+
+/// A line from a parent to one of its children, given by their ids so it can
+/// follow the nodes while they move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LayoutEdge {
+    pub parent_id: usize,
+    pub child_id: usize,
+}
+
+/// A whole tree laid out with its top-left corner at `(0, 0)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeLayout {
+    pub width: f32,
+    pub height: f32,
+    /// Nodes in preorder, so the root comes first.
+    pub nodes: Vec<LayoutNode>,
+    pub edges: Vec<LayoutEdge>,
+}
+
+impl TreeLayout {
+    pub fn new(snapshot: &BTreeSnapshot, config: &LayoutConfig) -> Self {
+        Self::with_collapsed(snapshot, config, &HashSet::new())
+    }
+
+    /// Lay out `snapshot`, hiding everything below the nodes whose ids are in
+    /// `collapsed`. Ids of leaves are ignored.
+    pub fn with_collapsed(
+        snapshot: &BTreeSnapshot,
+        config: &LayoutConfig,
+        collapsed: &HashSet<usize>,
+    ) -> Self {
+        let mut layout = Self {
+            width: 0.0,
+            height: 0.0,
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        };
+        if let Some(root) = &snapshot.root {
+            let measured = measure(root, config, collapsed);
+            layout.width = measured.width;
+            layout.height = measured.height;
+            layout.place(root, &measured, 0.0, 0.0, config);
+        }
+        layout
+    }
+
+    /// The laid out node with the given stable id.
+    pub fn node(&self, id: usize) -> Option<&LayoutNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    fn place(
+        &mut self,
+        node: &BTreeNodeSnapshot,
+        measured: &Measured,
+        left: f32,
+        top: f32,
+        config: &LayoutConfig,
+    ) {
+        let entries = node_entries(node);
+        self.nodes.push(LayoutNode {
+            id: node.id,
+            rect: Rect {
+                x: left + measured.node_x,
+                y: top,
+                width: measured.node_width,
+                height: measured.node_height,
+            },
+            label: entries.join(" | "),
+            entries,
+            depth: node.depth,
+            is_leaf: node.is_leaf,
+            key_count: node.key_count,
+            child_count: node.child_count,
+            collapsed: measured.summary.clone(),
+        });
+
+        let child_top = top + config.node_height + config.vertical_gap;
+        for ((child, child_measured), offset) in node
+            .children
+            .iter()
+            .zip(&measured.children)
+            .zip(&measured.child_offsets)
+        {
+            self.edges.push(LayoutEdge {
+                parent_id: node.id,
+                child_id: child.id,
+            });
+            self.place(child, child_measured, left + offset, child_top, config);
+        }
+    }
+}
+
+/// Size of a subtree, and where its root and child subtrees sit inside it.
+/// All offsets are relative to the subtree's left edge.
+struct Measured {
+    width: f32,
+    height: f32,
+    node_x: f32,
+    node_width: f32,
+    node_height: f32,
+    summary: Option<SubtreeSummary>,
+    child_offsets: Vec<f32>,
+    children: Vec<Measured>,
+}
+
+impl Measured {
+    fn node_center(&self) -> f32 {
+        self.node_x + self.node_width / 2.0
+    }
+}
+
+fn measure(
+    node: &BTreeNodeSnapshot,
+    config: &LayoutConfig,
+    collapsed: &HashSet<usize>,
+) -> Measured {
+    let text_width = |chars: usize| chars as f32 * config.char_width + config.node_padding * 2.0;
+    let mut node_width = text_width(node_entries(node).join(" | ").chars().count());
+
+    if node.children.is_empty() || collapsed.contains(&node.id) {
+        let summary = (!node.children.is_empty()).then(|| SubtreeSummary::new(node));
+        let mut node_height = config.node_height;
+        if let Some(summary) = &summary {
+            node_width = node_width.max(text_width(summary.to_string().chars().count()));
+            node_height *= 2.0;
+        }
+        return Measured {
+            width: node_width,
+            height: node_height,
+            node_x: 0.0,
+            node_width,
+            node_height,
+            summary,
+            child_offsets: Vec::new(),
+            children: Vec::new(),
+        };
+    }
+
+    // Pack the child subtrees side by side.
+    let children: Vec<Measured> = node
+        .children
+        .iter()
+        .map(|child| measure(child, config, collapsed))
+        .collect();
+    let mut child_offsets = Vec::with_capacity(children.len());
+    let mut next_left = 0.0;
+    for child in &children {
+        child_offsets.push(next_left);
+        next_left += child.width + config.horizontal_gap;
+    }
+    let children_width = next_left - config.horizontal_gap;
+
+    // Center the node over its outer children. A node wider than everything
+    // below it pokes out on either side, so shift the whole subtree right
+    // until nothing starts left of zero.
+    let first_center = child_offsets[0] + children[0].node_center();
+    let last_center =
+        child_offsets[children.len() - 1] + children[children.len() - 1].node_center();
+    let node_left = (first_center + last_center) / 2.0 - node_width / 2.0;
+    let shift = (-node_left).max(0.0);
+    let right = (node_left + node_width).max(children_width);
+
+    let max_child_height = children
+        .iter()
+        .map(|child| child.height)
+        .fold(0.0, f32::max);
+
+    Measured {
+        width: right + shift,
+        height: config.node_height + config.vertical_gap + max_child_height,
+        node_x: node_left + shift,
+        node_width,
+        node_height: config.node_height,
+        summary: None,
+        child_offsets: child_offsets
+            .into_iter()
+            .map(|offset| offset + shift)
+            .collect(),
+        children,
+    }
+}
+
+fn node_entries(node: &BTreeNodeSnapshot) -> Vec<String> {
+    node.keys
+        .iter()
+        .zip(&node.values)
+        .map(|(key, value)| format!("{key}:{value}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::btree::BTree;
+
+    /// Trees of several degrees and shapes, some with values much wider than
+    /// their children so that parents stick out past the subtree below.
+    fn sample_snapshots() -> Vec<BTreeSnapshot> {
+        let mut snapshots = Vec::new();
+        for min_degree in 2..=4 {
+            for count in [1, 2, 5, 17, 60] {
+                let mut tree = BTree::new(min_degree);
+                for i in 0..count {
+                    let key = (i * 37) % 101;
+                    tree.insert(key, "x".repeat((key % 7) as usize));
+                }
+                snapshots.push(tree.snapshot());
+            }
+        }
+
+        let mut wide = BTree::new(2);
+        wide.insert(2, "a very long value for the root".to_string());
+        wide.insert(1, String::new());
+        wide.insert(3, String::new());
+        wide.insert(4, String::new());
+        snapshots.push(wide.snapshot());
+        snapshots
+    }
+
+    #[test]
+    fn empty_tree_has_no_nodes() {
+        let layout = TreeLayout::new(
+            &BTree::<i32, i32>::new(2).snapshot(),
+            &LayoutConfig::default(),
+        );
+
+        assert!(layout.nodes.is_empty());
+        assert!(layout.edges.is_empty());
+        assert_eq!((layout.width, layout.height), (0.0, 0.0));
+    }
+
+    #[test]
+    fn nodes_never_overlap() {
+        let config = LayoutConfig::default();
+        for snapshot in sample_snapshots() {
+            let layout = TreeLayout::new(&snapshot, &config);
+            for (i, a) in layout.nodes.iter().enumerate() {
+                for b in &layout.nodes[i + 1..] {
+                    assert!(
+                        !a.rect.overlaps(&b.rect),
+                        "nodes {} and {} overlap",
+                        a.label,
+                        b.label
+                    );
+                    if a.depth == b.depth {
+                        let gap = if a.rect.x < b.rect.x {
+                            b.rect.x - a.rect.right()
+                        } else {
+                            a.rect.x - b.rect.right()
+                        };
+                        assert!(gap >= config.horizontal_gap - 1e-3);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parents_are_centered_over_their_children() {
+        for snapshot in sample_snapshots() {
+            let layout = TreeLayout::new(&snapshot, &LayoutConfig::default());
+            for parent in layout.nodes.iter().filter(|node| !node.is_leaf) {
+                let children: Vec<&LayoutNode> = layout
+                    .edges
+                    .iter()
+                    .filter(|edge| edge.parent_id == parent.id)
+                    .map(|edge| layout.node(edge.child_id).unwrap())
+                    .collect();
+                assert_eq!(children.len(), parent.child_count);
+
+                let first = children[0].rect.center().x;
+                let last = children[children.len() - 1].rect.center().x;
+                assert!((parent.rect.center().x - (first + last) / 2.0).abs() < 1e-3);
+                assert!(
+                    children
+                        .iter()
+                        .all(|child| child.rect.y > parent.rect.bottom())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn everything_fits_inside_the_reported_size() {
+        for snapshot in sample_snapshots() {
+            let layout = TreeLayout::new(&snapshot, &LayoutConfig::default());
+
+            assert_eq!(layout.edges.len(), layout.nodes.len() - 1);
+            for node in &layout.nodes {
+                assert!(node.rect.x >= 0.0 && node.rect.y >= 0.0);
+                assert!(node.rect.right() <= layout.width + 1e-3);
+                assert!(node.rect.bottom() <= layout.height + 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn collapsed_nodes_hide_their_subtree() {
+        let mut tree = BTree::new(2);
+        tree.extend((1..=30).map(|key| (key, key)));
+        let snapshot = tree.snapshot();
+        let root = snapshot.root.as_ref().unwrap();
+        let first_child = &root.children[0];
+        assert!(!first_child.is_leaf);
+
+        let collapsed = HashSet::from([first_child.id]);
+        let full = TreeLayout::new(&snapshot, &LayoutConfig::default());
+        let layout = TreeLayout::with_collapsed(&snapshot, &LayoutConfig::default(), &collapsed);
+        let node = layout.node(first_child.id).unwrap();
+        let summary = node.collapsed.as_ref().unwrap();
+
+        // Everything left of the root's first key, except the node's own keys.
+        let below_root: usize = root.keys[0].parse::<usize>().unwrap() - 1;
+        assert_eq!(summary.key_count, below_root - first_child.key_count);
+        assert_eq!(summary.node_count, full.nodes.len() - layout.nodes.len());
+        assert_eq!(summary.first_key, "1");
+        assert_eq!(summary.last_key, below_root.to_string());
+        assert!(
+            layout
+                .edges
+                .iter()
+                .all(|edge| edge.parent_id != first_child.id)
+        );
+        assert_eq!(node.rect.height, 2.0 * LayoutConfig::default().node_height);
+    }
+
+    #[test]
+    fn collapsing_keeps_nodes_apart() {
+        for snapshot in sample_snapshots() {
+            let Some(root) = &snapshot.root else { continue };
+            let collapsed = root
+                .children
+                .iter()
+                .step_by(2)
+                .map(|child| child.id)
+                .collect();
+            let layout =
+                TreeLayout::with_collapsed(&snapshot, &LayoutConfig::default(), &collapsed);
+            for (i, a) in layout.nodes.iter().enumerate() {
+                for b in &layout.nodes[i + 1..] {
+                    assert!(!a.rect.overlaps(&b.rect));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn labels_follow_the_snapshot() {
+        let tree = BTree::from([(1, "one"), (2, "two")]);
+        let layout = TreeLayout::new(&tree.snapshot(), &LayoutConfig::default());
+        let root = &layout.nodes[0];
+
+        assert_eq!(root.entries, vec!["1:one", "2:two"]);
+        assert_eq!(root.label, "1:one | 2:two");
+        assert_eq!(root.rect.width, 13.0 * 8.0 + 28.0);
+    }
+}
@@ -0,0 +1,361 @@
+//! `serde` support for `BTree`.
+//!
+//! The tree is written out node by node, so deserializing gives back the
+//! exact same shape instead of re-inserting every key. Because the input may
+//! come from anywhere, the decoded nodes are checked against the B-tree rules
+//! before they are accepted.
+
+use std::collections::HashSet;
+
+use serde::de::Error as _;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{BTree, Node};
+
+/// The on-disk layout of a tree. `len` is stored only so that the output is
+/// easy to read; it is checked against the real key count on the way back in.
+#[derive(Deserialize)]
+#[serde(rename = "BTree")]
+struct BTreeRepr<K, V> {
+    min_degree: usize,
+    len: usize,
+    root: Option<Node<K, V>>,
+}
+
+impl<K, V> Serialize for BTree<K, V>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("BTree", 3)?;
+        state.serialize_field("min_degree", &self.min_degree)?;
+        state.serialize_field("len", &self.len)?;
+        state.serialize_field("root", &self.root)?;
+        state.end()
+    }
+}
+
+impl<'de, K, V> Deserialize<'de> for BTree<K, V>
+where
+    K: Deserialize<'de> + Ord,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let repr = BTreeRepr::deserialize(deserializer)?;
+        check_tree(&repr).map_err(D::Error::custom)?;
+
+        let mut tree = BTree::new(repr.min_degree);
+        tree.root = repr.root;
+        tree.len = repr.len;
+        restore_node_ids(&mut tree).map_err(D::Error::custom)?;
+        Ok(tree)
+    }
+}
+
+/// Keep the saved node ids when they are usable, so snapshots taken after
+/// loading match the ones taken before saving. Input written by hand may
+/// leave ids out or repeat them; in that case every node is renumbered.
+fn restore_node_ids<K, V>(tree: &mut BTree<K, V>) -> Result<(), String> {
+    fn collect<K, V>(node: &Node<K, V>, ids: &mut Vec<usize>) {
+        ids.push(node.id);
+        node.children.iter().for_each(|child| collect(child, ids));
+    }
+
+    fn renumber<K, V>(node: &mut Node<K, V>, next_id: &mut usize) {
+        node.id = *next_id;
+        *next_id += 1;
+        node.children
+            .iter_mut()
+            .for_each(|child| renumber(child, next_id));
+    }
+
+    let Some(root) = tree.root.as_mut() else {
+        return Ok(());
+    };
+
+    let mut ids = Vec::new();
+    collect(root, &mut ids);
+    let unique: HashSet<_> = ids.iter().collect();
+
+    if unique.len() == ids.len() {
+        let max = ids.iter().max().copied().unwrap_or(0);
+        tree.next_node_id = max
+            .checked_add(1)
+            .ok_or_else(|| format!("node id {max} leaves no room for new nodes"))?;
+    } else {
+        renumber(root, &mut tree.next_node_id);
+    }
+    Ok(())
+}
+
+fn check_tree<K: Ord, V>(repr: &BTreeRepr<K, V>) -> Result<(), String> {
+    if repr.min_degree < 2 {
+        return Err(format!(
+            "minimum degree must be at least 2, found {}",
+            repr.min_degree
+        ));
+    }
+    // Nodes hold up to `2 * min_degree - 1` keys, which must not overflow,
+    // even for an empty tree that only grows later.
+    if repr.min_degree.checked_mul(2).is_none() {
+        return Err(format!("minimum degree {} is too large", repr.min_degree));
+    }
+
+    let mut leaf_depth = None;
+    let key_count = match &repr.root {
+        Some(root) => check_node(root, repr.min_degree, 0, None, None, &mut leaf_depth)?,
+        None => 0,
+    };
+
+    if key_count != repr.len {
+        return Err(format!(
+            "tree says it holds {} keys but its nodes hold {key_count}",
+            repr.len
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check one node and its subtree, returning the number of keys stored in it.
+///
+/// `lower` and `upper` are the parent keys on either side of this subtree, so
+/// every key here must fall strictly between them.
+fn check_node<K: Ord, V>(
+    node: &Node<K, V>,
+    min_degree: usize,
+    depth: usize,
+    lower: Option<&K>,
+    upper: Option<&K>,
+    leaf_depth: &mut Option<usize>,
+) -> Result<usize, String> {
+    let key_count = node.keys.len();
+    let too_large = || format!("minimum degree {min_degree} is too large");
+    let min_keys = if depth == 0 {
+        1
+    } else {
+        min_degree.checked_sub(1).ok_or_else(too_large)?
+    };
+    let max_keys = min_degree
+        .checked_mul(2)
+        .and_then(|keys| keys.checked_sub(1))
+        .ok_or_else(too_large)?;
+
+    if !(min_keys..=max_keys).contains(&key_count) {
+        return Err(format!(
+            "node at depth {depth} holds {key_count} keys, expected {min_keys}..={max_keys}"
+        ));
+    }
+
+    if node.values.len() != key_count {
+        return Err(format!(
+            "node at depth {depth} has {key_count} keys but {} values",
+            node.values.len()
+        ));
+    }
+
+    if node.keys.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err(format!("keys at depth {depth} are not strictly increasing"));
+    }
+
+    let first = &node.keys[0];
+    let last = &node.keys[key_count - 1];
+    if lower.is_some_and(|lower| first <= lower) || upper.is_some_and(|upper| last >= upper) {
+        return Err(format!(
+            "keys at depth {depth} fall outside the range set by their parent"
+        ));
+    }
+
+    if node.is_leaf() {
+        return match *leaf_depth {
+            Some(expected) if expected != depth => Err(format!(
+                "leaves found at depths {expected} and {depth}; all leaves must share a depth"
+            )),
+            _ => {
+                *leaf_depth = Some(depth);
+                Ok(key_count)
+            }
+        };
+    }
+
+    if node.children.len() != key_count + 1 {
+        return Err(format!(
+            "internal node at depth {depth} has {key_count} keys but {} children",
+            node.children.len()
+        ));
+    }
+
+    let mut total = key_count;
+    for (index, child) in node.children.iter().enumerate() {
+        let child_lower = if index == 0 {
+            lower
+        } else {
+            Some(&node.keys[index - 1])
+        };
+        let child_upper = node.keys.get(index).or(upper);
+        total += check_node(
+            child,
+            min_degree,
+            depth + 1,
+            child_lower,
+            child_upper,
+            leaf_depth,
+        )?;
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::btree::{BTree, BTreeSnapshot};
+
+    fn sample_tree() -> BTree<i32, String> {
+        let mut tree = BTree::new(2);
+        for key in [50, 40, 60, 30, 70, 20, 80, 10, 90, 0] {
+            tree.insert(key, format!("v{key}"));
+        }
+        tree
+    }
+
+    #[test]
+    fn json_round_trip_keeps_the_shape() {
+        let tree = sample_tree();
+
+        let json = serde_json::to_string(&tree).unwrap();
+        let restored: BTree<i32, String> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.len(), tree.len());
+        assert_eq!(restored.min_degree(), tree.min_degree());
+        assert_eq!(restored.snapshot(), tree.snapshot());
+    }
+
+    #[test]
+    fn binary_round_trip_keeps_the_shape() {
+        let tree = sample_tree();
+
+        let bytes = bincode::serialize(&tree).unwrap();
+        let restored: BTree<i32, String> = bincode::deserialize(&bytes).unwrap();
+
+        assert_eq!(restored.snapshot(), tree.snapshot());
+        assert_eq!(restored.get(&70), Some(&"v70".to_string()));
+    }
+
+    #[test]
+    fn shape_is_not_rebuilt_through_insert() {
+        // Inserting 1..=3 into a tree of minimum degree 2 would give a single
+        // leaf, so a root with two children proves the saved shape was kept.
+        let json = r#"{
+            "min_degree": 2,
+            "len": 3,
+            "root": {
+                "keys": [2],
+                "values": ["two"],
+                "children": [
+                    { "keys": [1], "values": ["one"], "children": [] },
+                    { "keys": [3], "values": ["three"], "children": [] }
+                ]
+            }
+        }"#;
+
+        let tree: BTree<i32, String> = serde_json::from_str(json).unwrap();
+        let root = tree.snapshot().root.unwrap();
+
+        assert_eq!(tree.len(), 3);
+        assert_eq!(root.keys, vec!["2"]);
+        assert_eq!(root.child_count, 2);
+    }
+
+    #[test]
+    fn missing_node_ids_are_filled_in() {
+        let json = r#"{"min_degree":2,"len":3,"root":{"keys":[2],"values":["b"],"children":[
+            {"keys":[1],"values":["a"],"children":[]},
+            {"keys":[3],"values":["c"],"children":[]}]}}"#;
+
+        let mut tree: BTree<i32, String> = serde_json::from_str(json).unwrap();
+        // The third insert splits [3, 4, 5], creating a fourth node.
+        for key in 4..=6 {
+            tree.insert(key, key.to_string());
+        }
+
+        let mut ids = Vec::new();
+        let mut stack = vec![tree.snapshot().root.unwrap()];
+        while let Some(node) = stack.pop() {
+            ids.push(node.id);
+            stack.extend(node.children);
+        }
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 4);
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let snapshot = sample_tree().snapshot();
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<BTreeSnapshot>(&json).unwrap(),
+            snapshot
+        );
+    }
+
+    #[test]
+    fn invalid_trees_are_rejected() {
+        let cases = [
+            // keys out of order
+            r#"{"min_degree":2,"len":2,"root":{"keys":[2,1],"values":["a","b"],"children":[]}}"#,
+            // too many keys for the minimum degree
+            r#"{"min_degree":2,"len":4,"root":{"keys":[1,2,3,4],"values":["a","b","c","d"],"children":[]}}"#,
+            // child key on the wrong side of its parent
+            r#"{"min_degree":2,"len":3,"root":{"keys":[2],"values":["b"],"children":[
+                {"keys":[3],"values":["c"],"children":[]},
+                {"keys":[4],"values":["d"],"children":[]}]}}"#,
+            // wrong number of children
+            r#"{"min_degree":2,"len":2,"root":{"keys":[2],"values":["b"],"children":[
+                {"keys":[1],"values":["a"],"children":[]}]}}"#,
+            // len does not match the stored keys
+            r#"{"min_degree":2,"len":5,"root":{"keys":[1],"values":["a"],"children":[]}}"#,
+            // minimum degree too small
+            r#"{"min_degree":1,"len":0,"root":null}"#,
+            r#"{"min_degree":0,"len":1,"root":{"keys":[1],"values":["a"],"children":[]}}"#,
+            // minimum degree so large that the key limit overflows
+            r#"{"min_degree":18446744073709551615,"len":0,"root":null}"#,
+            r#"{"min_degree":18446744073709551615,"len":1,"root":{"keys":[1],"values":["a"],"children":[]}}"#,
+            // no id is left for the next node
+            r#"{"min_degree":2,"len":1,"root":{"id":18446744073709551615,"keys":[1],"values":["a"],"children":[]}}"#,
+        ];
+
+        for json in cases {
+            assert!(
+                serde_json::from_str::<BTree<i32, String>>(json).is_err(),
+                "accepted invalid tree: {json}"
+            );
+        }
+    }
+
+    #[test]
+    fn leaves_must_share_a_depth() {
+        let json = r#"{"min_degree":2,"len":7,"root":{"keys":[2,4],"values":["b","d"],"children":[
+            {"keys":[1],"values":["a"],"children":[]},
+            {"keys":[3],"values":["c"],"children":[]},
+            {"keys":[6],"values":["f"],"children":[
+                {"keys":[5],"values":["e"],"children":[]},
+                {"keys":[7],"values":["g"],"children":[]}]}]}}"#;
+
+        let error = serde_json::from_str::<BTree<i32, String>>(json)
+            .map(|_| ())
+            .unwrap_err();
+
+        assert!(error.to_string().contains("depth"));
+    }
+}
@@ -27,10 +27,16 @@ impl<T> Node<T> {
     }
 }
 
-impl<T: PartialEq + Display + Clone> DoublyLinkedList<T> {
-    pub fn new() -> Self {
+impl<T> Default for DoublyLinkedList<T> {
+    fn default() -> Self {
         DoublyLinkedList { head: None }
     }
+}
+
+impl<T> DoublyLinkedList<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
     pub fn add(&mut self, value: T) {
         if self.head.is_none() {
@@ -39,53 +45,73 @@ impl<T: PartialEq + Display + Clone> DoublyLinkedList<T> {
         }
 
         let mut current_node = self.head.clone().unwrap();
-        while let Some(next_node) = {
-            // Use a block to limit the borrow scope
-            let next = current_node.borrow().next.clone();
-            next
-        } {
-            current_node = next_node;
+        loop {
+            // Clone the link in its own statement, so the borrow of
+            // `current_node` ends before it is reassigned.
+            let next_link = current_node.borrow().next.clone();
+            match next_link {
+                Some(next_node) => current_node = next_node,
+                None => break,
+            }
         }
         current_node.borrow_mut().next = Some(Rc::new(RefCell::new(Node::new(
             value,
             Some(Rc::clone(&current_node)),
         ))));
     }
+}
 
+impl<T: PartialEq> DoublyLinkedList<T> {
     pub fn remove(&mut self, value: T) {
-        if let Some(head_node) = self.head.clone() {
-            if head_node.borrow().value == value {
-                self.head = head_node.borrow().next.clone();
-                if let Some(node) = &self.head {
-                    node.borrow_mut().previous = None;
-                }
-                return;
+        if let Some(head_node) = self.head.clone()
+            && head_node.borrow().value == value
+        {
+            self.head = head_node.borrow().next.clone();
+            if let Some(node) = &self.head {
+                node.borrow_mut().previous = None;
             }
+            return;
         }
         let mut current_node = self.head.clone();
         while let Some(node) = current_node {
-            // Use a block to limit the borrow scope and avoid BorrowMutError
-            if let Some(next_node) = {
-                let _node = node.borrow().next.clone();
-                _node
-            } {
-                if next_node.borrow().value == value {
-                    match next_node.borrow().next.clone() {
-                        Some(_next_node) => {
-                            _next_node.borrow_mut().previous = Some(node.clone());
-                            node.borrow_mut().next = Some(_next_node);
-                        }
-                        None => {
-                            node.borrow_mut().next = None;
-                        }
+            // Clone the next link so no borrow of `node` is held while it is
+            // borrowed mutably below.
+            let next_link = node.borrow().next.clone();
+            if let Some(next_node) = next_link
+                && next_node.borrow().value == value
+            {
+                match next_node.borrow().next.clone() {
+                    Some(_next_node) => {
+                        _next_node.borrow_mut().previous = Some(node.clone());
+                        node.borrow_mut().next = Some(_next_node);
+                    }
+                    None => {
+                        node.borrow_mut().next = None;
                     }
-                    return;
                 }
+                return;
             }
             current_node = node.borrow().next.clone();
         }
     }
+}
+
+impl<T> Drop for DoublyLinkedList<T> {
+    fn drop(&mut self) {
+        // Neighbouring nodes hold strong links to each other, so they would
+        // never be freed on their own. Walk the list and cut both links of
+        // every node, so each one is freed as soon as the walk moves past it
+        // instead of through a chain of nested drops.
+        let mut current_node = self.head.take();
+        while let Some(node) = current_node {
+            let mut node = node.borrow_mut();
+            node.previous = None;
+            current_node = node.next.take();
+        }
+    }
+}
 
+impl<T: Display> DoublyLinkedList<T> {
     pub fn print_forward(&self) {
         self.print_to_writer(&mut stdout());
     }
@@ -105,10 +131,7 @@ mod test {
 
     use super::*;
 
-    fn check_output<T: PartialEq + Display + Clone>(
-        list: &DoublyLinkedList<T>,
-        expected_str: &str,
-    ) {
+    fn check_output<T: Display>(list: &DoublyLinkedList<T>, expected_str: &str) {
         let mut buffer = Cursor::new(Vec::new());
         list.print_to_writer(&mut buffer);
 
@@ -143,7 +166,7 @@ mod test {
         ));
         assert!(Rc::ptr_eq(
             &second_element,
-            &third_element.borrow().previous.as_ref().unwrap()
+            third_element.borrow().previous.as_ref().unwrap()
         ));
         assert!(third_element.borrow().next.is_none());
 
@@ -297,4 +320,53 @@ mod test {
         list.remove(4);
         check_output(&list, "");
     }
+
+    #[test]
+    fn test_values_without_clone_or_display() {
+        let mut list: DoublyLinkedList<Box<dyn Fn(i32) -> i32>> = DoublyLinkedList::new();
+        list.add(Box::new(|x| x + 1));
+        list.add(Box::new(|x| x * 10));
+
+        let first_element = list.head.as_ref().unwrap().clone();
+        let second_element = first_element.borrow().next.as_ref().unwrap().clone();
+        assert_eq!((first_element.borrow().value)(1), 2);
+        assert_eq!((second_element.borrow().value)(1), 10);
+    }
+
+    /// A list of `0..len`. `add` walks to the end every time, so the nodes
+    /// are linked directly here.
+    fn long_list(len: usize) -> DoublyLinkedList<usize> {
+        let mut list = DoublyLinkedList::new();
+        let mut last: Link<usize> = None;
+        for value in 0..len {
+            let node = Rc::new(RefCell::new(Node::new(value, last.clone())));
+            match &last {
+                Some(last_node) => last_node.borrow_mut().next = Some(Rc::clone(&node)),
+                None => list.head = Some(Rc::clone(&node)),
+            }
+            last = Some(node);
+        }
+        list
+    }
+
+    #[test]
+    fn test_drop_frees_every_node() {
+        let list = long_list(3);
+        check_output(&list, "0\n1\n2\n");
+        let first_element = Rc::downgrade(list.head.as_ref().unwrap());
+        let last_element = {
+            let second = list.head.as_ref().unwrap().borrow().next.clone().unwrap();
+            let third = second.borrow().next.clone().unwrap();
+            Rc::downgrade(&third)
+        };
+        drop(list);
+        assert!(first_element.upgrade().is_none());
+        assert!(last_element.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_drop_long_list() {
+        let list = long_list(3_000_000);
+        drop(list);
+    }
 }
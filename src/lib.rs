@@ -4,3 +4,4 @@ pub mod doubly_linked_list;
 pub mod efficient_dancing_links;
 pub mod linked_list;
 pub mod once_lock;
+pub mod persistent_list;
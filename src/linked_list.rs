@@ -1,79 +1,586 @@
+use std::cmp::Ordering;
 use std::fmt::Display;
 use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+mod cursor;
+mod iter;
+
+pub use cursor::CursorMut;
+pub use iter::{IntoIter, Iter, IterMut};
+
+/// A link to a node, or `None` at the end of the list.
+///
+/// Nodes are allocated as boxes and turned into raw pointers with
+/// `Box::leak`, as the standard library's list does. Every node is owned by
+/// the one link that leads to it from the front, and is freed with
+/// `Box::from_raw` once it has been unlinked. Working with raw pointers
+/// rather than `Box` keeps the `tail` pointer valid while the nodes before
+/// it are borrowed and moved around.
+type Link<T> = Option<NonNull<Node<T>>>;
 
 struct Node<T> {
     value: T,
-    next: Option<Box<Node<T>>>,
+    next: Link<T>,
 }
 
 pub struct LinkedList<T> {
-    root: Option<Box<Node<T>>>,
+    root: Link<T>,
+    /// The last node, so that appending does not walk the list. It does not
+    /// own the node; the link before it does.
+    tail: Link<T>,
+    len: usize,
+    /// Tells the compiler that the list owns its nodes and their values.
+    marker: PhantomData<Box<Node<T>>>,
 }
 
+// The list owns its nodes as a `Box` would, so it can cross threads whenever
+// its values can.
+unsafe impl<T: Send> Send for LinkedList<T> {}
+unsafe impl<T: Sync> Sync for LinkedList<T> {}
+
 impl<T> Node<T> {
-    fn new(value: T) -> Self {
-        Node { value, next: None }
+    /// Allocate a node and return the link that owns it.
+    fn new(value: T, next: Link<T>) -> NonNull<Self> {
+        NonNull::from(Box::leak(Box::new(Node { value, next })))
+    }
+
+    /// Free a node that no link leads to any more, and return its value.
+    ///
+    /// # Safety
+    ///
+    /// `node` must come from `Node::new` and must not be used afterwards.
+    unsafe fn free(node: NonNull<Self>) -> T {
+        // SAFETY: the caller guarantees `node` is a leaked box that nothing
+        // else refers to.
+        unsafe { Box::from_raw(node.as_ptr()) }.value
+    }
+}
+
+impl<T> Default for LinkedList<T> {
+    fn default() -> Self {
+        LinkedList {
+            root: None,
+            tail: None,
+            len: 0,
+            marker: PhantomData,
+        }
     }
 }
 
-impl<T: PartialEq + Display + Clone> LinkedList<T> {
+impl<T> LinkedList<T> {
     pub fn new() -> Self {
-        LinkedList { root: None }
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        // SAFETY: `root` points at a node owned by the list, which the shared
+        // borrow of the list keeps alive and unchanged.
+        self.root.map(|node| unsafe { &node.as_ref().value })
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        // SAFETY: as in `front`, for the last node.
+        self.tail.map(|node| unsafe { &node.as_ref().value })
     }
 
+    /// Visit every value from front to back.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter::new(self)
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut::new(self)
+    }
+
+    /// A cursor for editing the list, starting on the front node.
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut::new_front(self)
+    }
+
+    /// Same as `push_back`.
     pub fn add(&mut self, value: T) {
-        let new_node = Some(Box::new(Node::new(value)));
+        self.push_back(value);
+    }
+
+    /// Append `value` in O(1), by linking it after the tail.
+    pub fn push_back(&mut self, value: T) {
+        let new_node = Node::new(value, None);
+        match self.tail {
+            // SAFETY: `tail` points at the last node, which the list owns.
+            Some(tail) => unsafe { (*tail.as_ptr()).next = Some(new_node) },
+            None => self.root = Some(new_node),
+        }
+        self.tail = Some(new_node);
+        self.len += 1;
+    }
+
+    pub fn push_front(&mut self, value: T) {
+        let new_node = Node::new(value, self.root);
+        if self.tail.is_none() {
+            self.tail = Some(new_node);
+        }
+        self.root = Some(new_node);
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let root = self.root?;
+        // SAFETY: `root` is the front node, owned by the list. Once `root`
+        // moves past it, nothing leads to it any more.
+        self.root = unsafe { root.as_ref() }.next;
         if self.root.is_none() {
-            self.root = new_node;
+            self.tail = None;
+        }
+        self.len -= 1;
+        Some(unsafe { Node::free(root) })
+    }
+
+    /// Remove the last value. This is O(n): without links going backwards,
+    /// finding the node before the tail means walking from the front.
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.len <= 1 {
+            return self.pop_front();
+        }
+        let new_tail = self.node_at(self.len - 2);
+        // SAFETY: `new_tail` and the node after it are owned by the list, and
+        // once it is unlinked nothing leads to the old tail any more.
+        let old_tail = unsafe { (*new_tail.as_ptr()).next.take() }.unwrap();
+        self.tail = Some(new_tail);
+        self.len -= 1;
+        Some(unsafe { Node::free(old_tail) })
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.iter().nth(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.iter_mut().nth(index)
+    }
+
+    /// Index of the first value matching `pred`.
+    pub fn position<P>(&self, pred: P) -> Option<usize>
+    where
+        P: FnMut(&T) -> bool,
+    {
+        self.iter().position(pred)
+    }
+
+    /// Insert `value` so that it ends up at `index`, shifting the values
+    /// after it back. Gives the value back if `index` is past the end.
+    pub fn insert_at(&mut self, index: usize, value: T) -> Result<(), T> {
+        if index > self.len {
+            return Err(value);
+        }
+        if index == 0 {
+            self.push_front(value);
+            return Ok(());
+        }
+        if index == self.len {
+            self.push_back(value);
+            return Ok(());
+        }
+        let mut cursor = self.cursor_front_mut();
+        for _ in 1..index {
+            cursor.move_next();
+        }
+        cursor.insert_after(value);
+        Ok(())
+    }
+
+    /// Remove and return the value at `index`, or `None` if there is none.
+    pub fn remove_at(&mut self, index: usize) -> Option<T> {
+        if index >= self.len {
+            return None;
+        }
+        let mut cursor = self.cursor_front_mut();
+        for _ in 0..index {
+            cursor.move_next();
+        }
+        cursor.remove_current()
+    }
+
+    /// Keep only the values for which `keep` returns `true`, in order.
+    /// Returns how many values were removed.
+    pub fn retain<F>(&mut self, mut keep: F) -> usize
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut removed = 0;
+        let mut cursor = self.cursor_front_mut();
+        while let Some(value) = cursor.current() {
+            if keep(value) {
+                cursor.move_next();
+            } else {
+                cursor.remove_current();
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// Split the list in two at `index`. The list keeps the values before
+    /// it and the rest are returned. Returns `None` if `index` is past the
+    /// end.
+    pub fn split_off(&mut self, index: usize) -> Option<LinkedList<T>> {
+        if index > self.len {
+            return None;
+        }
+        if index == 0 {
+            return Some(std::mem::take(self));
+        }
+        if index == self.len {
+            return Some(LinkedList::new());
+        }
+        let new_tail = self.node_at(index - 1);
+        let back = LinkedList {
+            // SAFETY: `new_tail` is owned by the list. The nodes after it move
+            // to `back` together with the link that owns them.
+            root: unsafe { (*new_tail.as_ptr()).next.take() },
+            tail: self.tail,
+            len: self.len - index,
+            marker: PhantomData,
+        };
+        self.tail = Some(new_tail);
+        self.len = index;
+        Some(back)
+    }
+
+    /// Move every value of `other` to the back of this list in O(1),
+    /// leaving `other` empty.
+    pub fn append(&mut self, other: &mut LinkedList<T>) {
+        let Some(other_root) = other.root.take() else {
             return;
+        };
+        match self.tail {
+            // SAFETY: `tail` points at the last node, which the list owns.
+            Some(tail) => unsafe { (*tail.as_ptr()).next = Some(other_root) },
+            None => self.root = Some(other_root),
         }
-        let mut current_node = self.root.as_mut().unwrap();
-        while let Some(ref mut next_node) = current_node.next {
-            current_node = next_node;
+        self.tail = other.tail.take();
+        self.len += std::mem::take(&mut other.len);
+    }
+
+    /// Reverse the list in place by turning every link around. No node is
+    /// allocated or moved.
+    pub fn reverse(&mut self) {
+        let mut rest = self.root.take();
+        // The old front ends up last.
+        self.tail = rest;
+        while let Some(node) = rest {
+            // SAFETY: the nodes in `rest` are owned by the list.
+            rest = std::mem::replace(unsafe { &mut (*node.as_ptr()).next }, self.root);
+            self.root = Some(node);
         }
-        current_node.next = new_node;
     }
 
-    pub fn remove(&mut self, value: T) {
-        if let Some(root_node) = self.root.as_mut() {
-            if root_node.value == value {
-                self.root = root_node.next.take();
-                return;
+    /// Sort the list with `compare`, keeping equal values in their original
+    /// order.
+    ///
+    /// This is a bottom-up merge sort that relinks the nodes, so values are
+    /// never copied or moved. Runs are merged like carries in a binary
+    /// counter, see `SortState`.
+    ///
+    /// If `compare` panics, the list keeps all of its values, in an
+    /// unspecified order.
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut state = SortState {
+            rest: self.root.take(),
+            list: self,
+            runs: Vec::new(),
+            left: None,
+            right: None,
+            merged: None,
+        };
+        while let Some(node) = state.rest {
+            // SAFETY: the nodes in `rest` are owned by the list.
+            state.rest = unsafe { (*node.as_ptr()).next.take() };
+            state.right = Some(node);
+            let mut level = 0;
+            while let Some(run) = state.runs.get_mut(level).and_then(Option::take) {
+                state.left = Some(run);
+                state.merge(&mut compare);
+                level += 1;
+            }
+            let carry = state.right.take();
+            match state.runs.get_mut(level) {
+                Some(slot) => *slot = carry,
+                None => state.runs.push(carry),
             }
         }
 
-        let mut current_node = self.root.as_mut();
-        while let Some(node) = current_node {
-            if let Some(ref mut next_node) = node.next {
-                if next_node.value == value {
-                    node.next = next_node.next.take();
-                    return;
+        // Shorter runs hold later nodes, so each one goes on the right.
+        for level in 0..state.runs.len() {
+            state.left = state.runs[level].take();
+            state.merge(&mut compare);
+        }
+        // Dropping `state` puts the sorted nodes, all in `right`, back into
+        // the list.
+    }
+
+    /// Remove consecutive values that `same_bucket` puts together, keeping
+    /// the first of each group. `same_bucket` gets the later value first, as
+    /// in `Vec::dedup_by`.
+    ///
+    /// If `same_bucket` panics, the values removed up to then stay removed
+    /// and the rest stay in the list.
+    pub fn dedup_by<F>(&mut self, mut same_bucket: F)
+    where
+        F: FnMut(&mut T, &mut T) -> bool,
+    {
+        let Some(mut kept) = self.root else {
+            return;
+        };
+        // SAFETY: `kept` and the nodes after it are owned by the list. A
+        // duplicate is unlinked, and `tail` and `len` brought up to date,
+        // before it is freed, so the list is whole whenever `same_bucket` or
+        // a value's `drop` runs.
+        unsafe {
+            while let Some(next_node) = kept.as_ref().next {
+                if same_bucket(
+                    &mut (*next_node.as_ptr()).value,
+                    &mut (*kept.as_ptr()).value,
+                ) {
+                    (*kept.as_ptr()).next = next_node.as_ref().next;
+                    if self.tail == Some(next_node) {
+                        self.tail = Some(kept);
+                    }
+                    self.len -= 1;
+                    Node::free(next_node);
+                } else {
+                    kept = next_node;
                 }
             }
-            current_node = node.next.as_mut();
         }
     }
 
+    /// Remove consecutive values that map to the same key.
+    pub fn dedup_by_key<K, F>(&mut self, mut key: F)
+    where
+        K: PartialEq,
+        F: FnMut(&mut T) -> K,
+    {
+        self.dedup_by(|a, b| key(a) == key(b));
+    }
+
+    /// The node at `index`, which must be less than `len`.
+    fn node_at(&self, index: usize) -> NonNull<Node<T>> {
+        let mut node = self.root.unwrap();
+        for _ in 0..index {
+            // SAFETY: the list holds more than `index` nodes, all owned by it.
+            node = unsafe { node.as_ref() }.next.unwrap();
+        }
+        node
+    }
+}
+
+impl<T: Ord> LinkedList<T> {
+    /// Sort the list in ascending order. See `sort_by`.
+    pub fn sort(&mut self) {
+        self.sort_by(T::cmp);
+    }
+}
+
+impl<T: PartialEq> LinkedList<T> {
+    pub fn remove(&mut self, value: T) {
+        let mut cursor = self.cursor_front_mut();
+        while let Some(current) = cursor.current() {
+            if *current == value {
+                cursor.remove_current();
+                return;
+            }
+            cursor.move_next();
+        }
+    }
+
+    /// Remove every value equal to `value`. Returns how many were removed.
+    pub fn remove_all(&mut self, value: &T) -> usize {
+        self.retain(|other| other != value)
+    }
+
+    /// Remove consecutive repeated values, keeping the first of each run.
+    pub fn dedup(&mut self) {
+        self.dedup_by(|a, b| a == b);
+    }
+}
+
+impl<T: Display> LinkedList<T> {
     pub fn print(&self) {
         self.print_to_writer(&mut io::stdout());
     }
 
     pub fn print_to_writer<W: Write>(&self, writer: &mut W) {
-        let mut current_node = self.root.as_ref();
-        while let Some(node) = current_node {
-            writeln!(writer, "{}", node.value).unwrap();
-            current_node = node.next.as_ref();
+        for value in self {
+            writeln!(writer, "{value}").unwrap();
         }
     }
+}
 
+impl<T: Clone> LinkedList<T> {
     pub fn to_vector(&self) -> Vec<T> {
-        let mut v: Vec<T> = Vec::new();
-        let mut current_node = self.root.as_ref();
-        while let Some(node) = current_node {
-            v.push(node.value.clone());
-            current_node = node.next.as_ref();
+        self.iter().cloned().collect()
+    }
+}
+
+/// The nodes of a list while `sort_by` has taken them apart, held as chains
+/// that each end in `None`.
+///
+/// Dropping it joins the chains back into the list and finds the new tail.
+/// After a full sort every node is in `right`, in order. If a comparison
+/// panics instead, the list still gets every value back, in an unspecified
+/// order, rather than being left with a dangling tail.
+struct SortState<'a, T> {
+    list: &'a mut LinkedList<T>,
+    /// Nodes not sorted yet.
+    rest: Link<T>,
+    /// Runs merged like carries in a binary counter: `runs[i]` is empty or
+    /// holds a sorted run of `2^i` nodes, with longer runs holding earlier
+    /// nodes.
+    runs: Vec<Link<T>>,
+    /// The two runs being merged.
+    left: Link<T>,
+    right: Link<T>,
+    /// The part of the merge done so far.
+    merged: Link<T>,
+}
+
+impl<T> SortState<'_, T> {
+    /// Merge `left` into `right`. On ties the node from `left` comes first,
+    /// which keeps the sort stable.
+    fn merge<F>(&mut self, compare: &mut F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        // The end of `merged`, where the next node goes.
+        let mut last: Link<T> = None;
+        while let (Some(left_node), Some(right_node)) = (self.left, self.right) {
+            // SAFETY: both nodes are owned by the list.
+            let (left_value, right_value) =
+                unsafe { (&left_node.as_ref().value, &right_node.as_ref().value) };
+            let source = if compare(left_value, right_value) == Ordering::Greater {
+                &mut self.right
+            } else {
+                &mut self.left
+            };
+            let node = source.unwrap();
+            // SAFETY: `node` is the front of one of the runs, and `last` the
+            // end of `merged`.
+            unsafe {
+                *source = (*node.as_ptr()).next.take();
+                match last {
+                    Some(last) => (*last.as_ptr()).next = Some(node),
+                    None => self.merged = Some(node),
+                }
+            }
+            last = Some(node);
+        }
+
+        // One side ran out, so the rest of the other is already in place.
+        let rest = self.left.take().or(self.right.take());
+        match last {
+            // SAFETY: `last` is the end of `merged`.
+            Some(last) => unsafe { (*last.as_ptr()).next = rest },
+            None => self.merged = rest,
         }
-        v
+        self.right = self.merged.take();
+    }
+}
+
+impl<T> Drop for SortState<'_, T> {
+    fn drop(&mut self) {
+        let chains = [
+            self.right.take(),
+            self.merged.take(),
+            self.left.take(),
+            self.rest.take(),
+        ];
+        let list = &mut *self.list;
+        // `sort_by` took every node, so start again from an empty list.
+        list.root = None;
+        list.tail = None;
+        for front in chains.into_iter().chain(self.runs.drain(..)).flatten() {
+            match list.tail {
+                // SAFETY: `tail` ends the nodes joined so far, all owned by
+                // the list.
+                Some(tail) => unsafe { (*tail.as_ptr()).next = Some(front) },
+                None => list.root = Some(front),
+            }
+            let mut end = front;
+            // SAFETY: every chain holds nodes owned by the list.
+            while let Some(next) = unsafe { end.as_ref() }.next {
+                end = next;
+            }
+            list.tail = Some(end);
+        }
+    }
+}
+
+impl<T> Drop for LinkedList<T> {
+    fn drop(&mut self) {
+        // Free the nodes one at a time, front to back.
+        while self.pop_front().is_some() {}
+    }
+}
+
+impl<T> FromIterator<T> for LinkedList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for LinkedList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push_back(value);
+        }
+    }
+}
+
+impl<'a, T: Copy> Extend<&'a T> for LinkedList<T> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl<'a, T> IntoIterator for &'a LinkedList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut LinkedList<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T> IntoIterator for LinkedList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter::new(self)
     }
 }
 
@@ -81,6 +588,7 @@ impl<T: PartialEq + Display + Clone> LinkedList<T> {
 mod test {
     use super::*;
     use std::io::Cursor;
+    use std::panic::{self, AssertUnwindSafe};
 
     #[test]
     fn test_add() {
@@ -107,7 +615,7 @@ mod test {
     fn test_empty_list() {
         let linked_list: LinkedList<i32> = LinkedList::new();
         let v = linked_list.to_vector();
-        assert_eq!(v, vec![]);
+        assert_eq!(v, Vec::<i32>::new());
     }
 
     #[test]
@@ -120,7 +628,7 @@ mod test {
         linked_list.remove(1);
         linked_list.remove(3);
         let v = linked_list.to_vector();
-        assert_eq!(v, vec![]);
+        assert_eq!(v, Vec::<i32>::new());
     }
 
     #[test]
@@ -161,7 +669,7 @@ mod test {
         let mut linked_list: LinkedList<i32> = LinkedList::new();
         linked_list.remove(42);
         let v = linked_list.to_vector();
-        assert_eq!(v, vec![]);
+        assert_eq!(v, Vec::<i32>::new());
     }
 
     #[test]
@@ -177,4 +685,579 @@ mod test {
         let output = String::from_utf8(buffer.into_inner()).unwrap();
         assert_eq!(output, "1\n2\n3\n");
     }
+
+    #[test]
+    fn test_push_front_and_back() {
+        let mut linked_list = LinkedList::new();
+        linked_list.push_back(2);
+        linked_list.push_front(1);
+        linked_list.push_back(3);
+        assert_eq!(linked_list.to_vector(), vec![1, 2, 3]);
+        assert_eq!(linked_list.front(), Some(&1));
+        assert_eq!(linked_list.back(), Some(&3));
+        assert_eq!(linked_list.len(), 3);
+    }
+
+    #[test]
+    fn test_pop_front_and_back() {
+        let mut linked_list = LinkedList::new();
+        for value in 1..=4 {
+            linked_list.push_back(value);
+        }
+        assert_eq!(linked_list.pop_back(), Some(4));
+        assert_eq!(linked_list.pop_front(), Some(1));
+        assert_eq!(linked_list.back(), Some(&3));
+        assert_eq!(linked_list.pop_back(), Some(3));
+        assert_eq!(linked_list.pop_back(), Some(2));
+        assert_eq!(linked_list.pop_back(), None);
+        assert_eq!(linked_list.pop_front(), None);
+        assert!(linked_list.is_empty());
+        assert_eq!(linked_list.front(), None);
+        assert_eq!(linked_list.back(), None);
+
+        // The emptied list still appends correctly.
+        linked_list.push_back(5);
+        assert_eq!(linked_list.to_vector(), vec![5]);
+        assert_eq!(linked_list.back(), Some(&5));
+    }
+
+    #[test]
+    fn test_remove_keeps_tail_and_len() {
+        let mut linked_list = LinkedList::new();
+        linked_list.add(1);
+        linked_list.add(2);
+        linked_list.add(3);
+        linked_list.remove(3);
+        assert_eq!(linked_list.back(), Some(&2));
+        linked_list.add(4);
+        assert_eq!(linked_list.to_vector(), vec![1, 2, 4]);
+
+        linked_list.remove(42);
+        assert_eq!(linked_list.len(), 3);
+        linked_list.remove(1);
+        linked_list.remove(2);
+        linked_list.remove(4);
+        assert_eq!(linked_list.len(), 0);
+        assert_eq!(linked_list.back(), None);
+        linked_list.add(5);
+        assert_eq!(linked_list.to_vector(), vec![5]);
+    }
+
+    #[test]
+    fn test_million_elements() {
+        const COUNT: usize = 1_000_000;
+        let mut linked_list = LinkedList::new();
+        for value in 0..COUNT {
+            linked_list.push_back(value);
+        }
+        assert_eq!(linked_list.len(), COUNT);
+        assert_eq!(linked_list.front(), Some(&0));
+        assert_eq!(linked_list.back(), Some(&(COUNT - 1)));
+
+        for expected in 0..COUNT {
+            assert_eq!(linked_list.pop_front(), Some(expected));
+        }
+        assert!(linked_list.is_empty());
+    }
+
+    #[test]
+    fn test_values_without_clone_or_display() {
+        let mut linked_list: LinkedList<Box<dyn Fn(i32) -> i32>> = LinkedList::new();
+        linked_list.push_back(Box::new(|x| x + 1));
+        linked_list.push_back(Box::new(|x| x * 10));
+        assert_eq!(linked_list.len(), 2);
+        assert_eq!((linked_list.front().unwrap())(1), 2);
+        assert_eq!((linked_list.back().unwrap())(1), 10);
+        let first = linked_list.pop_front().unwrap();
+        assert_eq!(first(5), 6);
+    }
+
+    #[test]
+    fn test_iter() {
+        let linked_list: LinkedList<i32> = (1..=4).collect();
+        let mut iter = linked_list.iter();
+        assert_eq!(iter.len(), 4);
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.clone().copied().collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(iter.map(|value| value * 10).sum::<i32>(), 90);
+
+        let mut total = 0;
+        for value in &linked_list {
+            total += value;
+        }
+        assert_eq!(total, 10);
+        assert_eq!(LinkedList::<i32>::new().iter().next(), None);
+    }
+
+    #[test]
+    fn test_list_and_iterators_cross_threads() {
+        fn is_send<T: Send>(_: &T) {}
+        fn is_sync<T: Sync>(_: &T) {}
+
+        let mut linked_list: LinkedList<i32> = (1..=3).collect();
+        is_send(&linked_list);
+        is_sync(&linked_list);
+        is_send(&linked_list.iter());
+        is_sync(&linked_list.iter());
+        is_send(&linked_list.iter_mut());
+        is_sync(&linked_list.iter_mut());
+        is_send(&linked_list.cursor_front_mut());
+        is_sync(&linked_list.cursor_front_mut());
+        is_send(&linked_list.into_iter());
+
+        let linked_list: LinkedList<i32> = (1..=3).collect();
+        let sum = std::thread::scope(|scope| {
+            let iter = linked_list.iter();
+            scope.spawn(move || iter.sum::<i32>()).join().unwrap()
+        });
+        assert_eq!(sum, 6);
+    }
+
+    #[test]
+    fn test_iter_mut() {
+        let mut linked_list: LinkedList<i32> = (1..=3).collect();
+        for value in linked_list.iter_mut() {
+            *value *= 2;
+        }
+        for value in &mut linked_list {
+            *value += 1;
+        }
+        assert_eq!(linked_list.to_vector(), vec![3, 5, 7]);
+        assert_eq!(linked_list.back(), Some(&7));
+        linked_list.push_back(9);
+        assert_eq!(linked_list.back(), Some(&9));
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let linked_list: LinkedList<String> =
+            ["a", "b", "c"].into_iter().map(String::from).collect();
+        let mut iter = linked_list.into_iter();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some("a".to_string()));
+        assert_eq!(iter.collect::<Vec<_>>(), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut linked_list = LinkedList::new();
+        linked_list.push_back(1);
+        linked_list.extend(vec![2, 3]);
+        linked_list.extend(&[4, 5]);
+        assert_eq!(linked_list.len(), 5);
+        assert_eq!(linked_list.back(), Some(&5));
+        assert_eq!(linked_list.to_vector(), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_drop_long_list() {
+        let linked_list: LinkedList<usize> = (0..3_000_000).collect();
+        assert_eq!(linked_list.len(), 3_000_000);
+        drop(linked_list);
+    }
+
+    #[test]
+    fn test_cursor_moves_and_peeks() {
+        let mut linked_list: LinkedList<i32> = (1..=3).collect();
+        let mut cursor = linked_list.cursor_front_mut();
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(cursor.current(), Some(&mut 1));
+        assert_eq!(cursor.peek_next(), Some(&mut 2));
+
+        cursor.move_next();
+        *cursor.current().unwrap() = 20;
+        cursor.move_next();
+        assert_eq!(cursor.peek_next(), None);
+
+        // Past the back is the ghost position, which wraps to the front.
+        cursor.move_next();
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.peek_next(), Some(&mut 1));
+        cursor.move_next();
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(linked_list.to_vector(), vec![1, 20, 3]);
+
+        let mut empty: LinkedList<i32> = LinkedList::new();
+        let mut cursor = empty.cursor_front_mut();
+        assert_eq!(cursor.current(), None);
+        cursor.move_next();
+        assert_eq!(cursor.index(), None);
+    }
+
+    #[test]
+    fn test_cursor_insert_after() {
+        let mut linked_list: LinkedList<i32> = [1, 3].into_iter().collect();
+        let mut cursor = linked_list.cursor_front_mut();
+        cursor.insert_after(2);
+        assert_eq!(cursor.current(), Some(&mut 1));
+        cursor.move_next();
+        cursor.move_next();
+        cursor.insert_after(4);
+        cursor.move_next();
+        cursor.move_next();
+        // On the ghost position, inserting puts the value at the front.
+        cursor.insert_after(0);
+        assert_eq!(cursor.index(), None);
+
+        assert_eq!(linked_list.to_vector(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(linked_list.len(), 5);
+        assert_eq!(linked_list.back(), Some(&4));
+        linked_list.push_back(5);
+        assert_eq!(linked_list.to_vector(), vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_cursor_remove_current() {
+        let mut linked_list: LinkedList<i32> = (1..=6).collect();
+        let mut cursor = linked_list.cursor_front_mut();
+        // Drop the even values and double the odd ones as the stream passes.
+        while let Some(value) = cursor.current() {
+            if *value % 2 == 0 {
+                cursor.remove_current();
+            } else {
+                *value *= 2;
+                cursor.move_next();
+            }
+        }
+        assert_eq!(cursor.remove_current(), None);
+        assert_eq!(linked_list.to_vector(), vec![2, 6, 10]);
+        assert_eq!(linked_list.len(), 3);
+        assert_eq!(linked_list.back(), Some(&10));
+
+        let mut cursor = linked_list.cursor_front_mut();
+        assert_eq!(cursor.remove_current(), Some(2));
+        assert_eq!(cursor.remove_current(), Some(6));
+        assert_eq!(cursor.remove_current(), Some(10));
+        assert_eq!(cursor.index(), None);
+        assert!(linked_list.is_empty());
+        assert_eq!(linked_list.back(), None);
+        linked_list.push_back(7);
+        assert_eq!(linked_list.to_vector(), vec![7]);
+    }
+
+    #[test]
+    fn test_cursor_wraps_around_from_the_ghost() {
+        let mut linked_list: LinkedList<i32> = (1..=3).collect();
+        let mut cursor = linked_list.cursor_front_mut();
+        for _ in 0..3 {
+            cursor.move_next();
+        }
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.remove_current(), None);
+
+        // Wrapping around starts again from the front, which has no node
+        // before it, so removing it moves the root.
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(1));
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(3));
+        assert_eq!(cursor.index(), None);
+
+        assert_eq!(linked_list.to_vector(), vec![2]);
+        assert_eq!(linked_list.back(), Some(&2));
+        linked_list.push_back(4);
+        assert_eq!(linked_list.to_vector(), vec![2, 4]);
+    }
+
+    #[test]
+    fn test_cursor_splice_after() {
+        let mut linked_list: LinkedList<i32> = [1, 5].into_iter().collect();
+        let mut cursor = linked_list.cursor_front_mut();
+        cursor.splice_after((2..=4).collect());
+        assert_eq!(cursor.current(), Some(&mut 1));
+        cursor.splice_after(LinkedList::new());
+        cursor.move_next();
+        cursor.move_next();
+        cursor.move_next();
+        cursor.move_next();
+        cursor.splice_after([6, 7].into_iter().collect());
+        cursor.move_next();
+        cursor.move_next();
+        cursor.move_next();
+        cursor.splice_after([-1, 0].into_iter().collect());
+        assert_eq!(cursor.index(), None);
+
+        assert_eq!(linked_list.to_vector(), (-1..=7).collect::<Vec<_>>());
+        assert_eq!(linked_list.len(), 9);
+        assert_eq!(linked_list.back(), Some(&7));
+
+        let mut empty = LinkedList::new();
+        empty.cursor_front_mut().splice_after((1..=2).collect());
+        empty.push_back(3);
+        assert_eq!(empty.to_vector(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_reverse() {
+        let mut linked_list: LinkedList<i32> = (1..=4).collect();
+        linked_list.reverse();
+        assert_eq!(linked_list.to_vector(), vec![4, 3, 2, 1]);
+        assert_eq!(linked_list.back(), Some(&1));
+        linked_list.push_back(0);
+        assert_eq!(linked_list.to_vector(), vec![4, 3, 2, 1, 0]);
+
+        let mut empty: LinkedList<i32> = LinkedList::new();
+        empty.reverse();
+        assert!(empty.is_empty());
+        assert_eq!(empty.back(), None);
+    }
+
+    #[test]
+    fn test_sort() {
+        // A fixed shuffle of 0..1000 with every value repeated twice.
+        let values: Vec<i32> = (0..2000).map(|i| (i * 7919) % 1000).collect();
+        let mut linked_list: LinkedList<i32> = values.iter().copied().collect();
+        linked_list.sort();
+
+        let mut expected = values;
+        expected.sort();
+        assert_eq!(linked_list.to_vector(), expected);
+        assert_eq!(linked_list.len(), 2000);
+        assert_eq!(linked_list.back(), Some(&999));
+        linked_list.push_back(1000);
+        assert_eq!(linked_list.back(), Some(&1000));
+
+        let mut empty: LinkedList<i32> = LinkedList::new();
+        empty.sort();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_sort_by_is_stable() {
+        let mut linked_list: LinkedList<(i32, char)> = [
+            (2, 'a'),
+            (1, 'b'),
+            (2, 'c'),
+            (0, 'd'),
+            (1, 'e'),
+            (2, 'f'),
+            (0, 'g'),
+        ]
+        .into_iter()
+        .collect();
+        linked_list.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            linked_list.to_vector(),
+            vec![
+                (0, 'd'),
+                (0, 'g'),
+                (1, 'b'),
+                (1, 'e'),
+                (2, 'a'),
+                (2, 'c'),
+                (2, 'f')
+            ]
+        );
+
+        linked_list.sort_by(|a, b| b.1.cmp(&a.1));
+        assert_eq!(linked_list.front(), Some(&(0, 'g')));
+    }
+
+    #[test]
+    fn test_sort_relinks_nodes() {
+        let mut linked_list: LinkedList<i32> = [3, 1, 2].into_iter().collect();
+        let address_of = |list: &LinkedList<i32>, value: i32| {
+            list.iter().find(|&&v| v == value).unwrap() as *const i32
+        };
+        let before: Vec<_> = (1..=3)
+            .map(|value| address_of(&linked_list, value))
+            .collect();
+        linked_list.sort();
+        let after: Vec<_> = (1..=3)
+            .map(|value| address_of(&linked_list, value))
+            .collect();
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn test_sort_by_panic_keeps_the_list_usable() {
+        let values = [5, 3, 8, 1, 9, 2, 7, 4, 6, 0, 12, 11, 10];
+        // Panic at each comparison in turn, until a sort runs to the end.
+        for panic_at in 1.. {
+            let mut linked_list: LinkedList<i32> = values.into_iter().collect();
+            let mut comparisons = 0;
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                linked_list.sort_by(|a, b| {
+                    comparisons += 1;
+                    assert_ne!(comparisons, panic_at, "comparison failed");
+                    a.cmp(b)
+                });
+            }));
+
+            assert_eq!(linked_list.len(), values.len());
+            let mut kept = linked_list.to_vector();
+            assert_eq!(linked_list.back(), kept.last());
+            kept.sort();
+            assert_eq!(kept, (0..=12).collect::<Vec<_>>());
+
+            linked_list.push_back(13);
+            assert_eq!(linked_list.back(), Some(&13));
+            linked_list.sort();
+            assert_eq!(linked_list.to_vector(), (0..=13).collect::<Vec<_>>());
+
+            if result.is_ok() {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_dedup() {
+        let mut linked_list: LinkedList<i32> = [1, 1, 2, 3, 3, 3, 1, 4, 4].into_iter().collect();
+        linked_list.dedup();
+        assert_eq!(linked_list.to_vector(), vec![1, 2, 3, 1, 4]);
+        assert_eq!(linked_list.len(), 5);
+        assert_eq!(linked_list.back(), Some(&4));
+        linked_list.push_back(5);
+        assert_eq!(linked_list.to_vector(), vec![1, 2, 3, 1, 4, 5]);
+
+        let mut empty: LinkedList<i32> = LinkedList::new();
+        empty.dedup();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_dedup_by_key() {
+        let mut linked_list: LinkedList<i32> = [10, 11, 20, 25, 31, 12].into_iter().collect();
+        linked_list.dedup_by_key(|value| *value / 10);
+        assert_eq!(linked_list.to_vector(), vec![10, 20, 31, 12]);
+        assert_eq!(linked_list.back(), Some(&12));
+
+        let mut same: LinkedList<i32> = [5, 6, 7].into_iter().collect();
+        same.dedup_by_key(|_| ());
+        assert_eq!(same.to_vector(), vec![5]);
+        assert_eq!(same.back(), Some(&5));
+    }
+
+    #[test]
+    fn test_dedup_by_panic_keeps_the_list_usable() {
+        let mut linked_list: LinkedList<i32> = [1, 2, 2, 3, 3].into_iter().collect();
+        let mut calls = 0;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            linked_list.dedup_by(|a, b| {
+                calls += 1;
+                assert_ne!(calls, 4, "comparison failed");
+                a == b
+            });
+        }));
+
+        assert!(result.is_err());
+        // The first 2 was a duplicate and went before the panic.
+        assert_eq!(linked_list.to_vector(), vec![1, 2, 3, 3]);
+        assert_eq!(linked_list.len(), 4);
+        assert_eq!(linked_list.back(), Some(&3));
+        linked_list.push_back(4);
+        linked_list.dedup();
+        assert_eq!(linked_list.to_vector(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_get_and_position() {
+        let mut linked_list: LinkedList<i32> = [10, 20, 30].into_iter().collect();
+        assert_eq!(linked_list.get(0), Some(&10));
+        assert_eq!(linked_list.get(2), Some(&30));
+        assert_eq!(linked_list.get(3), None);
+
+        *linked_list.get_mut(1).unwrap() += 5;
+        assert_eq!(linked_list.get_mut(3), None);
+        assert_eq!(linked_list.to_vector(), vec![10, 25, 30]);
+
+        assert_eq!(linked_list.position(|&value| value > 20), Some(1));
+        assert_eq!(linked_list.position(|&value| value > 30), None);
+    }
+
+    #[test]
+    fn test_insert_at() {
+        let mut linked_list: LinkedList<i32> = [1, 3].into_iter().collect();
+        assert_eq!(linked_list.insert_at(1, 2), Ok(()));
+        assert_eq!(linked_list.insert_at(0, 0), Ok(()));
+        assert_eq!(linked_list.insert_at(4, 4), Ok(()));
+        assert_eq!(linked_list.insert_at(6, 6), Err(6));
+        assert_eq!(linked_list.to_vector(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(linked_list.len(), 5);
+        assert_eq!(linked_list.back(), Some(&4));
+
+        let mut empty = LinkedList::new();
+        assert_eq!(empty.insert_at(1, 1), Err(1));
+        assert_eq!(empty.insert_at(0, 1), Ok(()));
+        assert_eq!(empty.to_vector(), vec![1]);
+    }
+
+    #[test]
+    fn test_remove_at() {
+        let mut linked_list: LinkedList<i32> = (0..5).collect();
+        assert_eq!(linked_list.remove_at(5), None);
+        assert_eq!(linked_list.remove_at(4), Some(4));
+        assert_eq!(linked_list.back(), Some(&3));
+        assert_eq!(linked_list.remove_at(0), Some(0));
+        assert_eq!(linked_list.remove_at(1), Some(2));
+        assert_eq!(linked_list.to_vector(), vec![1, 3]);
+        assert_eq!(linked_list.len(), 2);
+        linked_list.push_back(5);
+        assert_eq!(linked_list.to_vector(), vec![1, 3, 5]);
+    }
+
+    #[test]
+    fn test_retain_and_remove_all() {
+        let mut linked_list: LinkedList<i32> = (1..=10).collect();
+        assert_eq!(linked_list.retain(|value| value % 3 != 0), 3);
+        assert_eq!(linked_list.to_vector(), vec![1, 2, 4, 5, 7, 8, 10]);
+        assert_eq!(linked_list.retain(|_| true), 0);
+
+        let mut linked_list: LinkedList<i32> = [2, 1, 2, 2, 3, 2].into_iter().collect();
+        assert_eq!(linked_list.remove_all(&2), 4);
+        assert_eq!(linked_list.remove_all(&7), 0);
+        assert_eq!(linked_list.to_vector(), vec![1, 3]);
+        assert_eq!(linked_list.len(), 2);
+        assert_eq!(linked_list.back(), Some(&3));
+        assert_eq!(linked_list.remove_all(&1) + linked_list.remove_all(&3), 2);
+        assert!(linked_list.is_empty());
+        assert_eq!(linked_list.back(), None);
+    }
+
+    #[test]
+    fn test_split_off() {
+        let mut linked_list: LinkedList<i32> = (0..5).collect();
+        assert!(linked_list.split_off(6).is_none());
+
+        let mut back = linked_list.split_off(2).unwrap();
+        assert_eq!(linked_list.to_vector(), vec![0, 1]);
+        assert_eq!(back.to_vector(), vec![2, 3, 4]);
+        assert_eq!((linked_list.len(), back.len()), (2, 3));
+        assert_eq!(linked_list.back(), Some(&1));
+        assert_eq!(back.back(), Some(&4));
+        linked_list.push_back(10);
+        back.push_back(20);
+        assert_eq!(linked_list.to_vector(), vec![0, 1, 10]);
+        assert_eq!(back.to_vector(), vec![2, 3, 4, 20]);
+
+        let end = back.split_off(4).unwrap();
+        assert!(end.is_empty());
+        let all = back.split_off(0).unwrap();
+        assert!(back.is_empty());
+        assert_eq!(all.to_vector(), vec![2, 3, 4, 20]);
+    }
+
+    #[test]
+    fn test_append() {
+        let mut linked_list: LinkedList<i32> = (0..2).collect();
+        let mut other: LinkedList<i32> = (2..4).collect();
+        linked_list.append(&mut other);
+        assert!(other.is_empty());
+        assert_eq!(other.back(), None);
+        assert_eq!(linked_list.to_vector(), vec![0, 1, 2, 3]);
+        assert_eq!(linked_list.len(), 4);
+        assert_eq!(linked_list.back(), Some(&3));
+
+        linked_list.append(&mut other);
+        assert_eq!(linked_list.len(), 4);
+        let mut empty = LinkedList::new();
+        empty.append(&mut linked_list);
+        empty.push_back(4);
+        assert_eq!(empty.to_vector(), vec![0, 1, 2, 3, 4]);
+        other.push_back(5);
+        assert_eq!(other.to_vector(), vec![5]);
+    }
 }
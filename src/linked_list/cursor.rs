@@ -0,0 +1,147 @@
+use super::{Link, LinkedList, Node};
+
+/// A cursor over a `LinkedList` that can edit the list where it stands.
+///
+/// The cursor sits on a node, or on a "ghost" position past the back of the
+/// list, from which moving forward wraps around to the front. It only moves
+/// forward, and every operation is O(1).
+pub struct CursorMut<'a, T> {
+    list: &'a mut LinkedList<T>,
+    /// The node the cursor is on, or `None` on the ghost position.
+    current: Link<T>,
+    /// The node before `current`, kept so that `current` can be unlinked
+    /// without walking from the front. `None` when `current` is the front
+    /// node or the cursor is on the ghost position.
+    previous: Link<T>,
+    /// Position of `current`, or the length of the list on the ghost.
+    index: usize,
+}
+
+// A `CursorMut` acts like the `&mut LinkedList` it borrows from.
+unsafe impl<T: Send> Send for CursorMut<'_, T> {}
+unsafe impl<T: Sync> Sync for CursorMut<'_, T> {}
+
+impl<'a, T> CursorMut<'a, T> {
+    pub(super) fn new_front(list: &'a mut LinkedList<T>) -> Self {
+        let current = list.root;
+        Self {
+            list,
+            current,
+            previous: None,
+            index: 0,
+        }
+    }
+
+    /// Position of the current node, or `None` on the ghost position.
+    pub fn index(&self) -> Option<usize> {
+        self.current.map(|_| self.index)
+    }
+
+    /// Step to the next node. From the back the cursor moves to the ghost
+    /// position, and from the ghost position back to the front.
+    pub fn move_next(&mut self) {
+        let Some(current) = self.current else {
+            self.current = self.list.root;
+            self.previous = None;
+            self.index = 0;
+            return;
+        };
+        // SAFETY: `current` points at a node owned by the list.
+        self.current = unsafe { current.as_ref() }.next;
+        // Stepping off the back lands on the ghost, which has no previous node.
+        self.previous = self.current.and(Some(current));
+        self.index += 1;
+    }
+
+    pub fn current(&mut self) -> Option<&mut T> {
+        // SAFETY: `current` points at a node owned by the list, and the
+        // returned borrow keeps the cursor from changing it.
+        self.current
+            .map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    /// The value after the current one, or the front value on the ghost
+    /// position.
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        let next = match self.current {
+            // SAFETY: `current` points at a node owned by the list.
+            Some(current) => unsafe { current.as_ref() }.next,
+            None => self.list.root,
+        };
+        // SAFETY: as above, for the node after it.
+        next.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    /// Insert `value` right after the current node. On the ghost position it
+    /// becomes the new front. The cursor stays where it is.
+    pub fn insert_after(&mut self, value: T) {
+        let Some(current) = self.current else {
+            self.list.push_front(value);
+            self.index += 1;
+            return;
+        };
+        // SAFETY: `current` points at a node owned by the list.
+        unsafe {
+            let new_node = Node::new(value, current.as_ref().next);
+            (*current.as_ptr()).next = Some(new_node);
+            if self.list.tail == Some(current) {
+                self.list.tail = Some(new_node);
+            }
+        }
+        self.list.len += 1;
+    }
+
+    /// Unlink the current node and return its value. The cursor moves on to
+    /// the node that followed it. Returns `None` on the ghost position.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let current = self.current?;
+        // SAFETY: `current` and `previous` point at nodes owned by the list,
+        // and `previous` is the node linking to `current`. Once it links past
+        // `current`, nothing leads to that node any more.
+        unsafe {
+            let next = current.as_ref().next;
+            match self.previous {
+                Some(previous) => (*previous.as_ptr()).next = next,
+                None => self.list.root = next,
+            }
+            if self.list.tail == Some(current) {
+                self.list.tail = self.previous;
+            }
+            self.current = next;
+            if next.is_none() {
+                self.previous = None;
+            }
+            self.list.len -= 1;
+            Some(Node::free(current))
+        }
+    }
+
+    /// Move every node of `other` into the list right after the current node,
+    /// keeping their order. On the ghost position they go to the front. The
+    /// cursor stays where it is.
+    pub fn splice_after(&mut self, mut other: LinkedList<T>) {
+        let Some(other_root) = other.root.take() else {
+            return;
+        };
+        let other_tail = other.tail.take().unwrap();
+        let other_len = std::mem::take(&mut other.len);
+
+        let link = match self.current {
+            // SAFETY: `current` points at a node owned by the list.
+            Some(current) => unsafe { &mut (*current.as_ptr()).next },
+            None => {
+                self.index += other_len;
+                &mut self.list.root
+            }
+        };
+        let rest = link.replace(other_root);
+        // SAFETY: `other_tail` is the last node of the chain that `other`
+        // handed over.
+        unsafe { (*other_tail.as_ptr()).next = rest };
+
+        if rest.is_none() {
+            self.list.tail = Some(other_tail);
+        }
+        self.list.len += other_len;
+    }
+}
@@ -0,0 +1,130 @@
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+
+use super::{Link, LinkedList, Node};
+
+/// Borrowing iterator over the values of a `LinkedList`, front to back.
+pub struct Iter<'a, T> {
+    next: Link<T>,
+    remaining: usize,
+    marker: PhantomData<&'a Node<T>>,
+}
+
+// An `Iter` only hands out shared references to the values, like the
+// `&LinkedList` it borrows from.
+unsafe impl<T: Sync> Send for Iter<'_, T> {}
+unsafe impl<T: Sync> Sync for Iter<'_, T> {}
+
+impl<'a, T> Iter<'a, T> {
+    pub(super) fn new(list: &'a LinkedList<T>) -> Self {
+        Self {
+            next: list.root,
+            remaining: list.len,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // SAFETY: the list is borrowed for `'a`, so its nodes stay alive and
+        // unchanged for that long.
+        let node = unsafe { self.next?.as_ref() };
+        self.next = node.next;
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            next: self.next,
+            remaining: self.remaining,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Iterator over mutable references to the values of a `LinkedList`, front
+/// to back.
+pub struct IterMut<'a, T> {
+    next: Link<T>,
+    remaining: usize,
+    marker: PhantomData<&'a mut Node<T>>,
+}
+
+// An `IterMut` acts like the `&mut LinkedList` it borrows from.
+unsafe impl<T: Send> Send for IterMut<'_, T> {}
+unsafe impl<T: Sync> Sync for IterMut<'_, T> {}
+
+impl<'a, T> IterMut<'a, T> {
+    pub(super) fn new(list: &'a mut LinkedList<T>) -> Self {
+        Self {
+            next: list.root,
+            remaining: list.len,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.next?.as_ptr();
+        // SAFETY: the list is borrowed mutably for `'a`, and each node is
+        // visited once, so the values handed out never overlap. Only the
+        // value is borrowed, never the whole node.
+        unsafe {
+            self.next = (*node).next;
+            self.remaining -= 1;
+            Some(&mut (*node).value)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T> FusedIterator for IterMut<'_, T> {}
+
+/// Owning iterator over the values of a `LinkedList`, front to back.
+pub struct IntoIter<T> {
+    list: LinkedList<T>,
+}
+
+impl<T> IntoIter<T> {
+    pub(super) fn new(list: LinkedList<T>) -> Self {
+        Self { list }
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.list.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.len, Some(self.list.len))
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
@@ -0,0 +1,257 @@
+use std::iter::FusedIterator;
+use std::rc::Rc;
+
+struct Node<T> {
+    value: T,
+    next: Option<Rc<Node<T>>>,
+}
+
+/// An immutable singly linked list whose nodes are shared between versions.
+///
+/// `prepend` returns a new list that points at the old one as its tail, so it
+/// is O(1) and the old list stays valid and unchanged. Cloning a list only
+/// copies a pointer, which makes it cheap to keep snapshots around, for
+/// example one per level of a backtracking search.
+pub struct PersistentList<T> {
+    head: Option<Rc<Node<T>>>,
+    len: usize,
+}
+
+impl<T> PersistentList<T> {
+    pub fn new() -> Self {
+        PersistentList { head: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// A new list with `value` in front of this one. Both lists share every
+    /// node of this one.
+    pub fn prepend(&self, value: T) -> Self {
+        PersistentList {
+            head: Some(Rc::new(Node {
+                value,
+                next: self.head.clone(),
+            })),
+            len: self.len + 1,
+        }
+    }
+
+    /// The first value of the list.
+    pub fn head(&self) -> Option<&T> {
+        self.head.as_ref().map(|node| &node.value)
+    }
+
+    /// The list without its first value, sharing its nodes with this one.
+    /// Returns `None` for an empty list.
+    pub fn tail(&self) -> Option<Self> {
+        self.head.as_ref().map(|node| PersistentList {
+            head: node.next.clone(),
+            len: self.len - 1,
+        })
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            next: self.head.as_deref(),
+            remaining: self.len,
+        }
+    }
+}
+
+impl<T> Default for PersistentList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for PersistentList<T> {
+    fn clone(&self) -> Self {
+        PersistentList {
+            head: self.head.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T> Drop for PersistentList<T> {
+    fn drop(&mut self) {
+        // Free the nodes only this list holds one at a time, rather than
+        // through one nested drop per node. The first node another list
+        // still points at ends the walk, since that list keeps the rest.
+        let mut next_node = self.head.take();
+        while let Some(node) = next_node {
+            match Rc::try_unwrap(node) {
+                Ok(mut node) => next_node = node.next.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+impl<T> FromIterator<T> for PersistentList<T> {
+    /// Build a list holding the values in iteration order.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let values: Vec<T> = iter.into_iter().collect();
+        values
+            .into_iter()
+            .rev()
+            .fold(Self::new(), |list, value| list.prepend(value))
+    }
+}
+
+impl<'a, T> IntoIterator for &'a PersistentList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Borrowing iterator over the values of a `PersistentList`, front to back.
+pub struct Iter<'a, T> {
+    next: Option<&'a Node<T>>,
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.next?;
+        self.next = node.next.as_deref();
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Iter {
+            next: self.next,
+            remaining: self.remaining,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn values(list: &PersistentList<i32>) -> Vec<i32> {
+        list.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_prepend() {
+        let empty = PersistentList::new();
+        let one = empty.prepend(1);
+        let two = one.prepend(2);
+        assert_eq!(values(&two), vec![2, 1]);
+        assert_eq!(two.len(), 2);
+        assert_eq!(two.head(), Some(&2));
+
+        // Older versions are untouched.
+        assert_eq!(values(&one), vec![1]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.head(), None);
+    }
+
+    #[test]
+    fn test_prepend_shares_the_tail() {
+        let base: PersistentList<i32> = (1..=3).collect();
+        let left = base.prepend(10);
+        let right = base.prepend(20);
+        assert_eq!(values(&left), vec![10, 1, 2, 3]);
+        assert_eq!(values(&right), vec![20, 1, 2, 3]);
+
+        let base_head = base.head.as_ref().unwrap();
+        for list in [&left, &right] {
+            let next = list.head.as_ref().unwrap().next.as_ref().unwrap();
+            assert!(Rc::ptr_eq(next, base_head));
+        }
+        // `base`, `left` and `right` all point at the same first node.
+        assert_eq!(Rc::strong_count(base_head), 3);
+    }
+
+    #[test]
+    fn test_tail() {
+        let list: PersistentList<i32> = (1..=3).collect();
+        let tail = list.tail().unwrap();
+        assert_eq!(values(&tail), vec![2, 3]);
+        assert_eq!(tail.len(), 2);
+        assert!(Rc::ptr_eq(
+            tail.head.as_ref().unwrap(),
+            list.head.as_ref().unwrap().next.as_ref().unwrap()
+        ));
+        assert!(PersistentList::<i32>::new().tail().is_none());
+        assert_eq!(values(&list), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_backtracking_snapshots() {
+        // Every subset of {1, 2, 3} as a path of choices, each level
+        // extending its parent's path without copying it.
+        fn subsets(remaining: &[i32], path: PersistentList<i32>, out: &mut Vec<Vec<i32>>) {
+            let Some((&first, rest)) = remaining.split_first() else {
+                let mut chosen: Vec<i32> = path.iter().copied().collect();
+                chosen.reverse();
+                out.push(chosen);
+                return;
+            };
+            subsets(rest, path.prepend(first), out);
+            subsets(rest, path, out);
+        }
+
+        let mut out = Vec::new();
+        subsets(&[1, 2, 3], PersistentList::new(), &mut out);
+        assert_eq!(
+            out,
+            vec![
+                vec![1, 2, 3],
+                vec![1, 2],
+                vec![1, 3],
+                vec![1],
+                vec![2, 3],
+                vec![2],
+                vec![3],
+                vec![],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drop_keeps_shared_nodes() {
+        let base: PersistentList<i32> = (1..=3).collect();
+        let longer = base.prepend(0);
+        drop(base);
+        assert_eq!(values(&longer), vec![0, 1, 2, 3]);
+
+        let tail = longer.tail().unwrap();
+        drop(longer);
+        assert_eq!(values(&tail), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_drop_long_list() {
+        let list: PersistentList<usize> = (0..3_000_000).collect();
+        let shared = list.tail().unwrap();
+        drop(list);
+        assert_eq!(shared.len(), 2_999_999);
+        drop(shared);
+    }
+}